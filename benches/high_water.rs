@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FILE_SIZE: u64 = 64 << 20;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn peak_rss(child: &Child) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", child.id())).ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn connect(port: u16) -> TcpStream {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => return stream,
+            Err(e) if Instant::now() >= deadline => panic!("server did not start: {e}"),
+            Err(_) => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+fn run(dir: &Path, high_water: u64) -> (Duration, Option<u64>) {
+    let port = free_port();
+
+    fs::write(dir.join("minhttp.toml"), format!(
+        "root = \"{}\"\nhost = \"localhost\"\nbuffer_high_water = {high_water}\n[http]\nport = {port}\n",
+        dir.join("www").display(),
+    )).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_webserver"))
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stream = connect(port);
+    let start = Instant::now();
+
+    stream.write_all(b"GET /large.bin HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+
+    let mut received = 0u64;
+    let mut buf = vec![0u8; 1 << 16];
+
+    loop {
+        match stream.read(&mut buf).unwrap() {
+            0 => break,
+            n => received += n as u64,
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let rss = peak_rss(&child);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(received > FILE_SIZE, "short response: {received} bytes");
+
+    (elapsed, rss)
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("minhttp-bench-{}", std::process::id()));
+    fs::create_dir_all(dir.join("www")).unwrap();
+    fs::write(dir.join("www/large.bin"), vec![0x5a; FILE_SIZE as usize]).unwrap();
+
+    for (name, high_water) in [("buffered", FILE_SIZE * 2), ("streamed", 65536)] {
+        let (elapsed, rss) = run(&dir, high_water);
+        let rss = rss.map_or("n/a".into(), |kb| format!("{kb} KiB"));
+
+        println!("{name:>8}: high water {high_water:>9} bytes, {elapsed:>10.2?}, peak rss {rss}");
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
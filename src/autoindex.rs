@@ -0,0 +1,94 @@
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use tokio::fs;
+
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    size: u64,
+    is_dir: bool,
+    modified: u64,
+}
+
+fn escape_html(s: &str) -> String {
+    s.chars().fold(String::new(), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+
+        out
+    })
+}
+
+fn escape_href(s: &str) -> String {
+    s.bytes().fold(String::new(), |mut out, b| {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+
+        out
+    })
+}
+
+async fn entries(dir: &Path, hide_dotfiles: bool) -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+    let mut read_dir = fs::read_dir(dir).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if hide_dotfiles && name.starts_with('.') {
+            continue;
+        }
+
+        let md = entry.metadata().await?;
+
+        entries.push(Entry {
+            name,
+            size: md.len(),
+            is_dir: md.is_dir(),
+            modified: md.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        });
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(entries)
+}
+
+pub async fn render_json(dir: &Path, hide_dotfiles: bool) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&entries(dir, hide_dotfiles).await?)?)
+}
+
+pub async fn render(dir: &Path, resource: &str, hide_dotfiles: bool) -> Result<Vec<u8>> {
+    let mut entries: Vec<String> = entries(dir, hide_dotfiles).await?.into_iter()
+        .map(|e| if e.is_dir { format!("{}/", e.name) } else { e.name })
+        .collect();
+
+    entries.sort();
+
+    let base = resource.trim_end_matches('/');
+    let title = escape_html(&format!("{base}/"));
+
+    let mut html = format!("<!DOCTYPE html>\n\n<html>\n    <head>\n        <title>Index of {title}</title>\n    </head>\n    <body>\n        <h1>Index of {title}</h1>\n        <ul>\n");
+
+    for name in entries {
+        html.push_str(&format!("            <li><a href=\"{}/{}\">{}</a></li>\n",
+            escape_href(base), escape_href(&name), escape_html(&name)));
+    }
+
+    html.push_str("        </ul>\n    </body>\n</html>\n");
+
+    Ok(html.into_bytes())
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::http::mime;
+use crate::http::message::{Limits, Method};
+use crate::pattern;
+use crate::writable::Modes;
+
+#[derive(Deserialize, Clone)]
+pub struct HttpConfig {
+    pub port: Option<u16>,
+    pub address: Option<String>,
+    pub root: Option<String>,
+    pub host: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HttpsConfig {
+    pub port: Option<u16>,
+    pub address: Option<String>,
+    pub key: PathBuf,
+    pub cert: PathBuf,
+    pub cert_dir: Option<PathBuf>,
+    pub root: Option<String>,
+    pub host: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LimitsConfig {
+    pub max_request_line: Option<usize>,
+    pub max_header_size: Option<usize>,
+    pub max_headers: Option<usize>,
+    pub strict: Option<bool>,
+    pub body_chunk_size: Option<usize>,
+}
+
+impl LimitsConfig {
+    pub fn limits(&self) -> Limits {
+        let default = Limits::default();
+
+        Limits {
+            max_request_line: self.max_request_line.unwrap_or(default.max_request_line),
+            max_header_size: self.max_header_size.unwrap_or(default.max_header_size),
+            max_headers: self.max_headers.unwrap_or(default.max_headers),
+            strict: self.strict.unwrap_or(default.strict),
+            body_chunk_size: self.body_chunk_size.unwrap_or(default.body_chunk_size),
+            body_timeout: default.body_timeout,
+            max_body: default.max_body,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CompressionConfig {
+    pub skip_extensions: Option<Vec<String>>,
+    pub types: Option<Vec<String>>,
+    pub cache_size: Option<u64>,
+    pub min_size: Option<u64>,
+}
+
+impl CompressionConfig {
+    pub fn compresses(&self, path: &Path) -> bool {
+        let mime = match path.extension().and_then(|e| e.to_str()).and_then(mime::from_extension) {
+            Some(mime) => mime,
+            None => return false,
+        };
+
+        let compressible = match &self.types {
+            Some(list) => list.iter().any(|t| mime::matches(t, mime)),
+            None => mime::DEFAULT_COMPRESSIBLE_TYPES.iter().any(|t| mime::matches(t, mime)),
+        };
+
+        compressible && !self.skips(path)
+    }
+
+    fn skips(&self, path: &Path) -> bool {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+        match &self.skip_extensions {
+            Some(list) => list.iter().any(|e| e.eq_ignore_ascii_case(ext)),
+            None => mime::DEFAULT_SKIP_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SlashPolicy {
+    #[default]
+    Merge,
+    Reject,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EtagMode {
+    #[default]
+    Metadata,
+    Content,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CacheConfig {
+    pub max_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CatchAllConfig {
+    pub root: Option<String>,
+    pub redirect: Option<String>,
+    pub retry_after: Option<u64>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ConnectConfig {
+    pub allow: Vec<String>,
+}
+
+impl ConnectConfig {
+    pub fn allows(&self, authority: &str) -> bool {
+        self.allow.iter().any(|p| pattern::matches(p, &authority.to_ascii_lowercase()))
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RouteConfig {
+    pub path: String,
+    pub headers: Option<HashMap<String, String>>,
+    pub preload: Option<Vec<String>>,
+    pub methods: Option<Vec<Method>>,
+    pub default_content_type: Option<String>,
+}
+
+impl RouteConfig {
+    fn specificity(&self) -> usize {
+        self.path.chars().filter(|&c| c != '*').count()
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DebugConfig {
+    pub echo_path: String,
+    pub allow_remote: Option<bool>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    File,
+    Index,
+    Spa,
+    Autoindex,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    pub root: String,
+    pub host: String,
+    pub http: Option<HttpConfig>,
+    pub https: Option<HttpsConfig>,
+    pub limits: Option<LimitsConfig>,
+    pub access_log: Option<PathBuf>,
+    pub compression: Option<CompressionConfig>,
+    pub hide_dotfiles: Option<bool>,
+    pub acme_challenge: Option<PathBuf>,
+    pub buffer_high_water: Option<u64>,
+    pub autoindex: Option<bool>,
+    pub max_connections_per_second: Option<u32>,
+    pub slashes: Option<SlashPolicy>,
+    pub idle_timeout: Option<u64>,
+    pub writable: Option<Vec<String>>,
+    pub handler_timeout: Option<u64>,
+    pub resolve: Option<Vec<Strategy>>,
+    pub index_files: Option<Vec<String>>,
+    pub spa_fallback: Option<String>,
+    pub debug: Option<DebugConfig>,
+    pub upgrade_insecure_requests: Option<bool>,
+    pub immutable: Option<Vec<String>>,
+    pub no_ranges: Option<Vec<String>>,
+    pub max_path_length: Option<usize>,
+    pub max_path_depth: Option<usize>,
+    pub negotiate_language: Option<bool>,
+    pub max_drain: Option<u64>,
+    pub etag: Option<EtagMode>,
+    pub cache: Option<CacheConfig>,
+    pub catch_all: Option<CatchAllConfig>,
+    pub check_sni: Option<bool>,
+    pub max_requests_in_flight: Option<usize>,
+    pub robots_txt: Option<String>,
+    pub default_favicon: Option<bool>,
+    pub accept_backoff: Option<u64>,
+    pub connect: Option<ConnectConfig>,
+    pub health_path: Option<String>,
+    pub shutdown_grace: Option<u64>,
+    pub headers: Option<HashMap<String, String>>,
+    pub routes: Option<Vec<RouteConfig>>,
+    pub max_body_size: Option<u64>,
+    pub max_connections_per_ip: Option<usize>,
+    pub disabled_method_status: Option<u32>,
+    pub maintenance_page: Option<PathBuf>,
+    pub tcp_keepalive: Option<u64>,
+    pub slow_request_threshold: Option<u64>,
+    pub upload_file_mode: Option<u32>,
+    pub upload_dir_mode: Option<u32>,
+    #[cfg(feature = "record")]
+    pub record: Option<PathBuf>,
+    pub default_content_type: Option<String>,
+    pub portable_paths: Option<bool>,
+    pub redirect_hosts: Option<Vec<String>>,
+    pub header_timeout: Option<u64>,
+    pub body_timeout: Option<u64>,
+    pub digest: Option<bool>,
+    pub write_timeout: Option<u64>,
+}
+
+const ENV_PREFIX: &str = "MINHTTP_";
+const SECTIONS: &[&str] = &["http", "https", "limits", "compression", "cache", "catch_all", "debug"];
+
+fn env_value(raw: &str) -> Value {
+    match format!("v = {raw}").parse::<Table>() {
+        Ok(mut table) => table.remove("v").unwrap_or(Value::String(raw.into())),
+        Err(_) => Value::String(raw.into()),
+    }
+}
+
+fn preload_destination(target: &str) -> Option<&'static str> {
+    let ext = Path::new(target.split(['?', '#']).next().unwrap_or(target)).extension()?.to_str()?;
+
+    match mime::from_extension(ext)?.split(';').next().unwrap_or("") {
+        "text/css" => Some("style"),
+        "text/javascript" | "application/javascript" => Some("script"),
+        m if m.starts_with("font/") => Some("font"),
+        m if m.starts_with("image/") => Some("image"),
+        m if m.starts_with("audio/") => Some("audio"),
+        m if m.starts_with("video/") => Some("video"),
+        _ => None,
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut table = match fs::read_to_string(path) {
+            Ok(contents) => contents.parse::<Table>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        for (name, raw) in env::vars_os() {
+            let (Some(name), Some(raw)) = (name.to_str(), raw.to_str()) else {
+                continue;
+            };
+
+            let Some(key) = name.strip_prefix(ENV_PREFIX).map(str::to_ascii_lowercase) else {
+                continue;
+            };
+
+            let section = SECTIONS.iter()
+                .find_map(|s| key.strip_prefix(s).and_then(|k| k.strip_prefix('_')).map(|k| (*s, k.to_string())));
+
+            match section {
+                Some((section, key)) => {
+                    let section = table.entry(section).or_insert_with(|| Value::Table(Table::new()));
+
+                    if let Value::Table(section) = section {
+                        section.insert(key, env_value(raw));
+                    }
+                },
+                None => {
+                    table.insert(key, env_value(raw));
+                },
+            }
+        }
+
+        Ok(table.try_into()?)
+    }
+
+    pub fn limits(&self) -> Limits {
+        let mut limits = self.limits.as_ref().map(|l| l.limits()).unwrap_or_default();
+        limits.body_timeout = self.body_timeout.map(Duration::from_secs).filter(|t| !t.is_zero());
+        limits.max_body = self.max_body_size;
+
+        limits
+    }
+
+    pub fn upload_modes(&self) -> Modes {
+        let default = Modes::default();
+
+        Modes {
+            file: self.upload_file_mode.unwrap_or(default.file),
+            dir: self.upload_dir_mode.unwrap_or(default.dir),
+        }
+    }
+
+    pub fn strategies(&self) -> Vec<Strategy> {
+        if let Some(strategies) = &self.resolve {
+            return strategies.clone();
+        }
+
+        let mut strategies = vec![Strategy::File, Strategy::Index, Strategy::Spa];
+
+        if self.autoindex.unwrap_or(false) {
+            strategies.push(Strategy::Autoindex);
+        }
+
+        strategies
+    }
+
+    pub fn cache_control(&self, resource: &str, file: &Path) -> Option<&'static str> {
+        let html = file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+
+        (!html && self.immutable.iter().flatten().any(|p| pattern::matches(p, resource)))
+            .then_some("public, max-age=31536000, immutable")
+    }
+
+    pub fn content_type(&self, path: &str, file: &Path) -> &str {
+        if let Some(mime) = file.extension().and_then(|e| e.to_str()).and_then(mime::from_extension) {
+            return mime;
+        }
+
+        self.routes(path).into_iter().rev()
+            .find_map(|r| r.default_content_type.as_deref())
+            .or(self.default_content_type.as_deref())
+            .unwrap_or(mime::DEFAULT_TYPE)
+    }
+
+    pub fn ranges(&self, resource: &str) -> bool {
+        !self.no_ranges.iter().flatten().any(|p| pattern::matches(p, resource))
+    }
+
+    pub fn routes(&self, path: &str) -> Vec<&RouteConfig> {
+        let mut routes: Vec<&RouteConfig> = self.routes.iter().flatten()
+            .filter(|r| pattern::matches(&r.path, path))
+            .collect();
+
+        routes.sort_by_key(|r| r.specificity());
+        routes
+    }
+
+    pub fn headers(&self, path: &str) -> HashMap<String, String> {
+        let mut headers = self.headers.clone().unwrap_or_default();
+
+        for (name, value) in self.routes(path).into_iter().flat_map(|r| r.headers.iter().flatten()) {
+            headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+            headers.insert(name.clone(), value.clone());
+        }
+
+        headers
+    }
+
+    pub fn methods(&self, path: &str) -> Option<&[Method]> {
+        self.routes(path).into_iter().rev().find_map(|r| r.methods.as_deref())
+    }
+
+    pub fn preload(&self, path: &str) -> Option<String> {
+        let links: Vec<String> = self.routes(path).into_iter()
+            .flat_map(|r| r.preload.iter().flatten())
+            .map(|target| match preload_destination(target) {
+                Some(dest) => format!("<{target}>; rel=preload; as={dest}"),
+                None => format!("<{target}>; rel=preload"),
+            })
+            .collect();
+
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+
+    pub fn index_files(&self) -> Vec<String> {
+        self.index_files.clone().unwrap_or(vec!["index.html".into()])
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+pub fn metadata(md: &Metadata) -> String {
+    let mtime = md.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    format!("\"{:x}-{:x}\"", md.len(), mtime)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+
+    out
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+#[derive(Default)]
+pub struct ContentHashes {
+    entries: Mutex<HashMap<PathBuf, (SystemTime, [u8; 32])>>,
+}
+
+impl ContentHashes {
+    pub async fn get(&self, path: &Path, md: &Metadata) -> io::Result<String> {
+        let digest = self.digest(path, md).await?;
+        Ok(format!("\"{}\"", digest[..16].iter().map(|b| format!("{b:02x}")).collect::<String>()))
+    }
+
+    pub async fn digest(&self, path: &Path, md: &Metadata) -> io::Result<[u8; 32]> {
+        let mtime = md.modified()?;
+
+        if let Some((cached, digest)) = self.entries.lock().unwrap().get(path) {
+            if *cached == mtime {
+                return Ok(*digest);
+            }
+        }
+
+        let mut file = File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut chunk = vec![0u8; 16384];
+
+        loop {
+            let n = file.read(&mut chunk).await?;
+
+            if n == 0 {
+                break;
+            }
+
+            hasher.update(&chunk[..n]);
+        }
+
+        let digest: [u8; 32] = hasher.finalize().into();
+        self.entries.lock().unwrap().insert(path.into(), (mtime, digest));
+
+        Ok(digest)
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+struct Entry {
+    mtime: SystemTime,
+    len: u64,
+    data: Arc<Vec<u8>>,
+    used: u64,
+    inserted: Instant,
+}
+
+struct Entries {
+    map: HashMap<PathBuf, Entry>,
+    size: u64,
+    tick: u64,
+}
+
+pub struct FileCache {
+    entries: Mutex<Entries>,
+    max_size: u64,
+    max_file_size: u64,
+}
+
+impl FileCache {
+    pub fn new(max_size: u64, max_file_size: u64) -> Self {
+        let entries = Entries { map: HashMap::new(), size: 0, tick: 0 };
+        Self { entries: Mutex::new(entries), max_size, max_file_size }
+    }
+
+    pub fn accepts(&self, md: &Metadata) -> bool {
+        md.len() <= self.max_file_size.min(self.max_size)
+    }
+
+    pub fn get(&self, path: &Path, md: &Metadata) -> Option<(Arc<Vec<u8>>, Duration)> {
+        let mtime = md.modified().ok()?;
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        let entry = entries.map.get_mut(path).filter(|e| e.mtime == mtime && e.len == md.len())?;
+        entry.used = tick;
+
+        Some((entry.data.clone(), entry.inserted.elapsed()))
+    }
+
+    pub fn insert(&self, path: &Path, md: &Metadata, data: Arc<Vec<u8>>) {
+        let Ok(mtime) = md.modified() else {
+            return;
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(old) = entries.map.remove(path) {
+            entries.size -= old.data.len() as u64;
+        }
+
+        while entries.size + data.len() as u64 > self.max_size {
+            let Some(oldest) = entries.map.iter().min_by_key(|(_, e)| e.used).map(|(p, _)| p.clone()) else {
+                return;
+            };
+
+            if let Some(old) = entries.map.remove(&oldest) {
+                entries.size -= old.data.len() as u64;
+            }
+        }
+
+        entries.tick += 1;
+        entries.size += data.len() as u64;
+
+        let used = entries.tick;
+        entries.map.insert(path.into(), Entry { mtime, len: md.len(), data, used, inserted: Instant::now() });
+    }
+}
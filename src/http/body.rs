@@ -0,0 +1,374 @@
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use std::time::Duration;
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf, Take};
+
+use crate::http::timeout::IdleTimeout;
+
+const MAX_CHUNK_LINE: usize = 4096;
+
+enum State {
+    Size,
+    Data(u64),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+pub struct Chunked<'a, R> {
+    inner: &'a mut R,
+    state: State,
+    line: Vec<u8>,
+    read: u64,
+    max: Option<u64>,
+    trailers: usize,
+}
+
+impl<'a, R: AsyncBufRead + Unpin> Chunked<'a, R> {
+    pub fn new(inner: &'a mut R, max: Option<u64>, max_trailers: usize) -> Self {
+        Self { inner, state: State::Size, line: vec![], read: 0, max, trailers: max_trailers }
+    }
+
+    pub fn done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    fn poll_line(&mut self, cx: &mut Context<'_>, limit: usize) -> Poll<io::Result<Vec<u8>>> {
+        loop {
+            let buf = ready!(Pin::new(&mut *self.inner).poll_fill_buf(cx))?;
+
+            if buf.is_empty() {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "chunked body ended early")));
+            }
+
+            let (taken, done) = match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (buf.len(), false),
+            };
+
+            self.line.extend_from_slice(&buf[..taken]);
+            Pin::new(&mut *self.inner).consume(taken);
+
+            if self.line.len() > limit + 2 {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, "chunked body line is too long")));
+            }
+
+            if done {
+                let mut line = mem::take(&mut self.line);
+                line.pop();
+
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+
+                return Poll::Ready(Ok(line));
+            }
+        }
+    }
+
+    fn parse_size(line: &[u8]) -> io::Result<u64> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size");
+
+        let line = std::str::from_utf8(line).map_err(|_| invalid())?;
+        let size = line.split(';').next().unwrap_or("").trim();
+
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        u64::from_str_radix(size, 16).map_err(|_| invalid())
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for Chunked<'_, R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = data.len().min(buf.remaining());
+
+        buf.put_slice(&data[..n]);
+        self.consume(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for Chunked<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        let remaining = loop {
+            match this.state {
+                State::Data(n) if n > 0 => break n,
+                State::Data(_) => this.state = State::DataEnd,
+                State::Done => return Poll::Ready(Ok(&[])),
+                State::Size => {
+                    let size = Self::parse_size(&ready!(this.poll_line(cx, MAX_CHUNK_LINE))?)?;
+
+                    if this.max.is_some_and(|max| this.read.saturating_add(size) > max) {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::FileTooLarge, "request body exceeds max_body_size")));
+                    }
+
+                    this.read += size;
+                    this.state = if size == 0 { State::Trailers } else { State::Data(size) };
+                },
+                State::DataEnd => {
+                    if !ready!(this.poll_line(cx, 0))?.is_empty() {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, "chunk data is not followed by CRLF")));
+                    }
+
+                    this.state = State::Size;
+                },
+                State::Trailers => {
+                    let line = ready!(this.poll_line(cx, this.trailers))?;
+                    this.trailers = this.trailers.saturating_sub(line.len());
+
+                    if line.is_empty() {
+                        this.state = State::Done;
+                    }
+                },
+            }
+        };
+
+        let buf = ready!(Pin::new(&mut *this.inner).poll_fill_buf(cx))?;
+
+        if buf.is_empty() {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "chunked body ended early")));
+        }
+
+        let n = buf.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+
+        Poll::Ready(Ok(&buf[..n]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        if let State::Data(n) = &mut self.state {
+            *n -= amt as u64;
+        }
+
+        Pin::new(&mut *self.inner).consume(amt)
+    }
+}
+
+enum Framing<'a, R> {
+    Length(Take<&'a mut R>),
+    Chunked(Chunked<'a, R>),
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for Framing<'_, R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Length(inner) => Pin::new(inner).poll_read(cx, buf),
+            Self::Chunked(inner) => Pin::new(inner).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for Framing<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        match self.get_mut() {
+            Self::Length(inner) => Pin::new(inner).poll_fill_buf(cx),
+            Self::Chunked(inner) => Pin::new(inner).poll_fill_buf(cx),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        match self.get_mut() {
+            Self::Length(inner) => Pin::new(inner).consume(amt),
+            Self::Chunked(inner) => Pin::new(inner).consume(amt),
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+struct Capture {
+    bytes: Vec<u8>,
+    pending: Vec<u8>,
+    limit: usize,
+}
+
+#[cfg(feature = "record")]
+impl Capture {
+    fn push(&mut self, data: &[u8]) {
+        let room = self.limit.saturating_sub(self.bytes.len());
+        self.bytes.extend_from_slice(&data[..data.len().min(room)]);
+    }
+}
+
+pub struct RequestBody<'a, R> {
+    inner: IdleTimeout<Framing<'a, R>>,
+    chunk_size: usize,
+    #[cfg(feature = "record")]
+    capture: Option<Capture>,
+}
+
+impl<'a, R: AsyncBufRead + Unpin> RequestBody<'a, R> {
+    pub fn new(reader: &'a mut R, length: u64, chunk_size: usize, timeout: Option<Duration>) -> Self {
+        Self::framed(Framing::Length(reader.take(length)), chunk_size, timeout)
+    }
+
+    pub fn chunked(chunked: Chunked<'a, R>, chunk_size: usize, timeout: Option<Duration>) -> Self {
+        Self::framed(Framing::Chunked(chunked), chunk_size, timeout)
+    }
+
+    fn framed(framing: Framing<'a, R>, chunk_size: usize, timeout: Option<Duration>) -> Self {
+        Self {
+            inner: IdleTimeout::new(framing, timeout),
+            chunk_size: chunk_size.max(1),
+            #[cfg(feature = "record")]
+            capture: None,
+        }
+    }
+
+    #[cfg(feature = "record")]
+    pub fn capture(&mut self, limit: usize) {
+        self.capture = Some(Capture { bytes: vec![], pending: vec![], limit });
+    }
+
+    #[cfg(feature = "record")]
+    pub fn captured(&self) -> Option<&[u8]> {
+        self.capture.as_ref().map(|c| c.bytes.as_slice())
+    }
+
+    pub fn remaining(&self) -> Option<u64> {
+        match self.inner.get_ref() {
+            Framing::Length(inner) => Some(inner.limit()),
+            Framing::Chunked(inner) => inner.done().then_some(0),
+        }
+    }
+
+    pub async fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![];
+        self.read_to_end(&mut buf).await?;
+
+        Ok(buf)
+    }
+
+    pub async fn copy_to<W: AsyncWrite + Unpin>(&mut self, writer: &mut W) -> io::Result<u64> {
+        let mut chunk = vec![0u8; self.chunk_size.min(self.remaining().unwrap_or(u64::MAX) as usize)];
+        let mut copied = 0;
+
+        while !chunk.is_empty() {
+            let n = self.read(&mut chunk).await?;
+
+            if n == 0 {
+                break;
+            }
+
+            writer.write_all(&chunk[..n]).await?;
+            copied += n as u64;
+        }
+
+        Ok(copied)
+    }
+
+    pub async fn drain(&mut self) -> io::Result<u64> {
+        self.copy_to(&mut tokio::io::sink()).await
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for RequestBody<'_, R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        #[cfg(feature = "record")]
+        let before = buf.filled().len();
+
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        #[cfg(feature = "record")]
+        if let (Poll::Ready(Ok(())), Some(capture)) = (&poll, &mut self.capture) {
+            capture.push(&buf.filled()[before..]);
+        }
+
+        poll
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for RequestBody<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_fill_buf(cx);
+
+        #[cfg(feature = "record")]
+        if let (Poll::Ready(Ok(data)), Some(capture)) = (&poll, &mut this.capture) {
+            capture.pending = data[..data.len().min(capture.limit.saturating_sub(capture.bytes.len()))].to_vec();
+        }
+
+        poll
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        #[cfg(feature = "record")]
+        if let Some(capture) = &mut self.capture {
+            let pending = mem::take(&mut capture.pending);
+            capture.push(&pending[..amt.min(pending.len())]);
+        }
+
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::AsyncBufReadExt;
+
+    #[tokio::test]
+    async fn reads_chunked_body_by_line() {
+        let mut input: &[u8] = b"6;name=value\r\nfirst\n\r\n7\r\nsecond\n\r\n0\r\nX-Trailer: yes\r\n\r\nGET / HTTP/1.1\r\n";
+        let mut body = RequestBody::chunked(Chunked::new(&mut input, None, 1024), 16, None);
+
+        let mut lines = vec![];
+        let mut line = String::new();
+
+        while body.read_line(&mut line).await.unwrap() > 0 {
+            lines.push(mem::take(&mut line));
+        }
+
+        assert_eq!(lines, ["first\n", "second\n"]);
+        assert_eq!(body.remaining(), Some(0));
+        assert_eq!(body.read_line(&mut line).await.unwrap(), 0);
+        drop(body);
+
+        assert_eq!(input, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[tokio::test]
+    async fn reads_length_body_by_line() {
+        let mut input: &[u8] = b"one\ntwo\nnext request";
+        let mut body = RequestBody::new(&mut input, 8, 16, None);
+
+        let mut lines = vec![];
+        let mut line = String::new();
+
+        while body.read_line(&mut line).await.unwrap() > 0 {
+            lines.push(mem::take(&mut line));
+        }
+
+        assert_eq!(lines, ["one\n", "two\n"]);
+        drop(body);
+
+        assert_eq!(input, b"next request");
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_chunked_body() {
+        let mut input: &[u8] = b"10\r\n0123456789abcdef\r\n0\r\n\r\n";
+        let mut body = RequestBody::chunked(Chunked::new(&mut input, Some(8), 1024), 16, None);
+
+        let err = body.bytes().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_chunk_size() {
+        let mut input: &[u8] = b"zz\r\nabc\r\n0\r\n\r\n";
+        let mut body = RequestBody::chunked(Chunked::new(&mut input, None, 1024), 16, None);
+
+        let err = body.bytes().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+use std::mem;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::http::negotiate;
+
+pub fn accepts(accept_encoding: &str, coding: &str) -> bool {
+    let mut wildcard = false;
+
+    for (name, q) in negotiate::qvalues(accept_encoding) {
+        if name.eq_ignore_ascii_case(coding) {
+            return q > 0.0;
+        }
+
+        if name == "*" {
+            wildcard = q > 0.0;
+        }
+    }
+
+    wildcard
+}
+
+pub fn gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+pub struct GzipReader<R> {
+    inner: R,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R> GzipReader<R> {
+    pub fn new(inner: R, chunk_size: usize) -> Self {
+        Self {
+            inner,
+            encoder: Some(GzEncoder::new(Vec::new(), Compression::default())),
+            input: vec![0u8; chunk_size.max(1)],
+            output: vec![],
+            position: 0,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for GzipReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.position < this.output.len() {
+                let n = buf.remaining().min(this.output.len() - this.position);
+                buf.put_slice(&this.output[this.position..this.position + n]);
+                this.position += n;
+
+                return Poll::Ready(Ok(()));
+            }
+
+            let Some(encoder) = this.encoder.as_mut() else {
+                return Poll::Ready(Ok(()));
+            };
+
+            let mut input = ReadBuf::new(&mut this.input);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut input))?;
+
+            this.position = 0;
+            this.output = match input.filled() {
+                [] => this.encoder.take().map(|e| e.finish()).transpose()?.unwrap_or_default(),
+                data => {
+                    encoder.write_all(data)?;
+                    mem::take(encoder.get_mut())
+                },
+            };
+        }
+    }
+}
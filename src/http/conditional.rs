@@ -0,0 +1,119 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::http::date::DateTime;
+use crate::http::message::Method;
+use crate::http::request::Request;
+
+#[derive(PartialEq)]
+pub enum Precondition {
+    Proceed,
+    NotModified,
+    Failed,
+}
+
+fn strong_eq(a: &str, b: &str) -> bool {
+    !a.starts_with("W/") && !b.starts_with("W/") && a == b
+}
+
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+fn list_matches(list: &str, etag: &str, eq: fn(&str, &str) -> bool) -> bool {
+    list.split(',').map(str::trim).any(|tag| eq(tag, etag))
+}
+
+pub fn evaluate(request: &Request, etag: Option<&str>) -> Precondition {
+    if let Some(if_match) = request.header::<String>("If-Match") {
+        let matched = match etag {
+            Some(etag) => if_match.trim() == "*" || list_matches(&if_match, etag, strong_eq),
+            None => false,
+        };
+
+        if !matched {
+            return Precondition::Failed;
+        }
+    }
+
+    if let Some(if_none_match) = request.header::<String>("If-None-Match") {
+        let matched = match etag {
+            Some(etag) => if_none_match.trim() == "*" || list_matches(&if_none_match, etag, weak_eq),
+            None => false,
+        };
+
+        if matched {
+            return match request.method {
+                Method::Get | Method::Head => Precondition::NotModified,
+                _ => Precondition::Failed,
+            };
+        }
+    }
+
+    Precondition::Proceed
+}
+
+pub fn unmodified_since(request: &Request, modified: Option<SystemTime>) -> Precondition {
+    if request.header::<String>("If-Match").is_some() {
+        return Precondition::Proceed;
+    }
+
+    let Some(since) = request.header::<String>("If-Unmodified-Since").and_then(|v| v.parse::<DateTime>().ok()) else {
+        return Precondition::Proceed;
+    };
+
+    let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+    match modified {
+        Some(modified) if secs(modified) > secs(since.into()) => Precondition::Failed,
+        _ => Precondition::Proceed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::http::message::Version;
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> Request {
+        let headers = headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Request::new(method, "/", Version::Http11, headers).unwrap()
+    }
+
+    #[test]
+    fn if_match_requires_a_strong_match() {
+        let etag = Some("\"abc\"");
+
+        assert!(evaluate(&request(Method::Put, &[("If-Match", "\"abc\"")]), etag) == Precondition::Proceed);
+        assert!(evaluate(&request(Method::Put, &[("If-Match", "W/\"abc\"")]), etag) == Precondition::Failed);
+        assert!(evaluate(&request(Method::Put, &[("If-Match", "*")]), None) == Precondition::Failed);
+    }
+
+    #[test]
+    fn if_none_match_uses_a_weak_match() {
+        let etag = Some("\"abc\"");
+
+        assert!(evaluate(&request(Method::Get, &[("If-None-Match", "\"x\", W/\"abc\"")]), etag) == Precondition::NotModified);
+        assert!(evaluate(&request(Method::Put, &[("If-None-Match", "*")]), etag) == Precondition::Failed);
+        assert!(evaluate(&request(Method::Get, &[("If-None-Match", "\"x\"")]), etag) == Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_unmodified_since_compares_seconds() {
+        let since = [("If-Unmodified-Since", "Sun, 06 Nov 1994 08:49:37 GMT")];
+        let at = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        assert!(unmodified_since(&request(Method::Put, &since), Some(at + Duration::from_millis(500))) == Precondition::Proceed);
+        assert!(unmodified_since(&request(Method::Put, &since), Some(at + Duration::from_secs(1))) == Precondition::Failed);
+        assert!(unmodified_since(&request(Method::Put, &since), None) == Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_overrides_if_unmodified_since() {
+        let headers = [("If-Match", "*"), ("If-Unmodified-Since", "Sun, 06 Nov 1994 08:49:37 GMT")];
+
+        assert!(unmodified_since(&request(Method::Put, &headers), Some(SystemTime::now())) == Precondition::Proceed);
+    }
+}
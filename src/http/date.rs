@@ -0,0 +1,133 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    pub fn month_name(&self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(value: SystemTime) -> Self {
+        let secs = value.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+        let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+}
+
+impl std::str::FromStr for DateTime {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, rest) = s.trim().split_once(", ").ok_or(())?;
+        let parts: Vec<&str> = rest.split(' ').collect();
+
+        let [day, month, year, time, "GMT"] = parts[..] else {
+            return Err(());
+        };
+
+        let mut time = time.split(':').map(|p| p.parse::<u32>().map_err(|_| ()));
+        let (hour, minute, second) = (time.next().ok_or(())??, time.next().ok_or(())??, time.next().ok_or(())??);
+
+        let datetime = Self {
+            year: year.parse().map_err(|_| ())?,
+            month: MONTHS.iter().position(|m| *m == month).ok_or(())? as u32 + 1,
+            day: day.parse().map_err(|_| ())?,
+            hour,
+            minute,
+            second,
+        };
+
+        if !(1..=9999).contains(&datetime.year) || !(1..=31).contains(&datetime.day) || hour > 23 || minute > 59 || second > 60 {
+            return Err(());
+        }
+
+        Ok(datetime)
+    }
+}
+
+impl From<DateTime> for SystemTime {
+    fn from(value: DateTime) -> Self {
+        let year = value.year - if value.month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (value.month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + value.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+
+        let secs = days * 86400 + value.hour as i64 * 3600 + value.minute as i64 * 60 + value.second as i64;
+
+        UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_http_dates() {
+        let date: DateTime = "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap();
+
+        assert_eq!((date.year, date.month, date.day), (1994, 11, 6));
+        assert_eq!((date.hour, date.minute, date.second), (8, 49, 37));
+        assert_eq!(date.month_name(), "Nov");
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        assert!("Sun, 06 Nov 1994 08:49:37 UTC".parse::<DateTime>().is_err());
+        assert!("Sun, 06 Foo 1994 08:49:37 GMT".parse::<DateTime>().is_err());
+        assert!("Sun, 32 Nov 1994 08:49:37 GMT".parse::<DateTime>().is_err());
+        assert!("Sun, 06 Nov 1994 24:00:00 GMT".parse::<DateTime>().is_err());
+        assert!("06 Nov 1994 08:49:37 GMT".parse::<DateTime>().is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_years() {
+        assert!("Sun, 06 Nov 0 08:49:37 GMT".parse::<DateTime>().is_err());
+        assert!("Sun, 06 Nov 10000 08:49:37 GMT".parse::<DateTime>().is_err());
+        assert!("Sun, 06 Nov 9223372036854775807 08:49:37 GMT".parse::<DateTime>().is_err());
+    }
+
+    #[test]
+    fn round_trips_system_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        let date = DateTime::from(time);
+
+        assert_eq!((date.year, date.month, date.day), (1994, 11, 6));
+        assert_eq!(SystemTime::from(date), time);
+    }
+}
@@ -2,7 +2,8 @@ use std::fmt::{self, Display};
 use std::error::Error;
 use std::collections::HashMap;
 use std::iter::Iterator;
-use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite};
 
 use anyhow::Result;
 
@@ -15,6 +16,15 @@ pub enum MessageParseError {
     ConnectionClosed,
     RequestLineParse,
     Header,
+    UriTooLong,
+    HeadersTooLarge,
+    UnexpectedResponse,
+    DuplicateContentLength,
+    Http2Preface,
+    ConflictingLength,
+    UnsupportedTransferEncoding,
+    InvalidContentLength,
+    UnsupportedVersion,
 }
 
 impl Display for MessageParseError {
@@ -23,12 +33,48 @@ impl Display for MessageParseError {
             Self::Header => "failed to parse header",
             Self::ConnectionClosed => "the connection was closed",
             Self::RequestLineParse => "failed to parse request line",
+            Self::UriTooLong => "the request line is too long",
+            Self::HeadersTooLarge => "the header section is too large",
+            Self::UnexpectedResponse => "received a response where a request was expected",
+            Self::DuplicateContentLength => "the message has more than one Content-Length header",
+            Self::Http2Preface => "received an HTTP/2 connection preface",
+            Self::ConflictingLength => "the message has both Transfer-Encoding and Content-Length headers",
+            Self::UnsupportedTransferEncoding => "the request uses a Transfer-Encoding other than chunked",
+            Self::InvalidContentLength => "the Content-Length header is not a valid length",
+            Self::UnsupportedVersion => "the request uses an HTTP version that is not spoken over this connection",
         })
     }
 }
 
 impl Error for MessageParseError {}
 
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub max_request_line: usize,
+    pub max_header_size: usize,
+    pub max_headers: usize,
+    pub strict: bool,
+    pub body_chunk_size: usize,
+    pub body_timeout: Option<Duration>,
+    pub max_body: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_request_line: 8192,
+            max_header_size: 16384,
+            max_headers: 100,
+            strict: true,
+            body_chunk_size: 16384,
+            body_timeout: None,
+            max_body: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Method {
     Get,
     Head,
@@ -93,7 +139,9 @@ impl fmt::Display for Method {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum Version {
+    Http10,
     Http11,
     Http2,
     Http3,
@@ -121,10 +169,11 @@ impl std::str::FromStr for Version {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "HTTP/1.0" => Ok(Self::Http10),
             "HTTP/1.1" => Ok(Self::Http11),
             "HTTP/2" => Ok(Self::Http2),
             "HTTP/3" => Ok(Self::Http3),
-            _ => Err(VersionParseError::InvalidVersion.into())
+            _ => Err(VersionParseError::InvalidVersion)
         }
     }
 }
@@ -132,6 +181,7 @@ impl std::str::FromStr for Version {
 impl fmt::Display for Version {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
+            Self::Http10 => "HTTP/1.0",
             Self::Http11 => "HTTP/1.1",
             Self::Http2 => "HTTP/2",
             Self::Http3 => "HTTP/3",
@@ -145,7 +195,11 @@ pub enum Message {
 }
 
 impl Message {
-    async fn parse<R: AsyncBufRead + Unpin>(request_line: &str, headers: HashMap<String, String>, body: &mut R) -> Result<Self> {
+    async fn parse<R: AsyncBufRead + Unpin>(request_line: &str, headers: HashMap<String, String>, body: Option<&mut R>) -> Result<Self> {
+        if request_line == "PRI * HTTP/2.0" {
+            return Err(MessageParseError::Http2Preface.into());
+        }
+
         let parts = request_line.splitn(3, ' ').collect::<Vec<&str>>();
 
         if parts.len() != 3 {
@@ -153,10 +207,16 @@ impl Message {
         }
 
         if let Ok(method) = parts[0].parse::<Method>() {
-            let (method, resource, version) = (method, parts[1], parts[2]);
-            Ok(Request::new(method, resource, version.parse()?, headers, body).await?.into())
+            let (method, resource, version) = (method, parts[1], parts[2].parse()?);
+
+            if !matches!(version, Version::Http10 | Version::Http11) {
+                return Err(MessageParseError::UnsupportedVersion.into());
+            }
+
+            Ok(Request::new(method, resource, version, headers)?.into())
         } else if let Ok(version) = parts[0].parse::<Version>() {
             let (version, code, message) = (version, parts[1], parts[2]);
+            let body = body.ok_or(MessageParseError::UnexpectedResponse)?;
             Ok(Response::new(version, code.parse()?, message, headers, body).await?.into())
         } else {
             Err(MessageParseError::RequestLineParse.into())
@@ -164,21 +224,93 @@ impl Message {
     }
 }
 
-impl<R: AsyncBufRead + Unpin> Deserialize<R> for Message {
-    async fn deserialize(reader: &mut R) -> Result<Self> {
-        let mut lines = reader.lines();
+impl Message {
+    async fn read_line<R>(reader: &mut R, limit: usize, err: MessageParseError) -> Result<Option<String>>
+    where
+        R: AsyncBufRead + Unpin
+    {
+        let mut buf = vec![];
+
+        if reader.take(limit as u64 + 2).read_until(b'\n', &mut buf).await? == 0 {
+            return Ok(None);
+        }
+
+        if buf.ends_with(b"\n") {
+            buf.pop();
+
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+
+        if buf.len() > limit {
+            return Err(err.into());
+        }
+
+        Ok(Some(String::from_utf8(buf)?))
+    }
 
-        let request_line = lines.next_line().await?
+    pub async fn read_with_limits<R>(reader: &mut R, limits: &Limits) -> Result<Self>
+    where
+        R: AsyncBufRead + Unpin
+    {
+        let (request_line, headers) = Self::read_head(reader, limits).await?;
+        let mut message = Self::parse(&request_line, headers, Some(&mut *reader)).await?;
+
+        if let Self::Request(req) = &mut message {
+            req.read_body(reader).await?;
+        }
+
+        Ok(message)
+    }
+
+    pub async fn read_head_with_limits<R>(reader: &mut R, limits: &Limits) -> Result<Self>
+    where
+        R: AsyncBufRead + Unpin
+    {
+        let (request_line, headers) = Self::read_head(reader, limits).await?;
+        Self::parse::<R>(&request_line, headers, None).await
+    }
+
+    async fn read_head<R>(reader: &mut R, limits: &Limits) -> Result<(String, HashMap<String, String>)>
+    where
+        R: AsyncBufRead + Unpin
+    {
+        let request_line = Self::read_line(reader, limits.max_request_line, MessageParseError::UriTooLong).await?
             .ok_or(MessageParseError::ConnectionClosed)?;
 
         let mut headers: HashMap<String, String> = HashMap::new();
+        let mut remaining = limits.max_header_size;
+        let mut lines = 0;
+
+        while let Some(line) = Self::read_line(reader, remaining, MessageParseError::HeadersTooLarge).await?.filter(|l| !l.is_empty()) {
+            if lines >= limits.max_headers {
+                return Err(MessageParseError::HeadersTooLarge.into());
+            }
+
+            lines += 1;
+            remaining -= line.len();
 
-        while let Some(line) = lines.next_line().await?.filter(|l| !l.is_empty()) {
             let (left, right) = line.split_once(": ").ok_or(MessageParseError::Header)?;
+
+            if left.eq_ignore_ascii_case("Content-Length") {
+                let previous = headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("Content-Length"));
+
+                if previous.is_some_and(|(_, v)| limits.strict || v.trim() != right.trim()) {
+                    return Err(MessageParseError::DuplicateContentLength.into());
+                }
+            }
+
             headers.insert(left.into(), right.into());
         }
 
-        Message::parse(&request_line, headers, lines.get_mut()).await
+        Ok((request_line, headers))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Deserialize<R> for Message {
+    async fn deserialize(reader: &mut R) -> Result<Self> {
+        Self::read_with_limits(reader, &Limits::default()).await
     }
 }
 
@@ -189,6 +321,13 @@ impl Serialize for Message {
             Self::Response(res) => res.serialize(),
         }
     }
+
+    async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<()> {
+        match self {
+            Self::Request(req) => req.write_to(w).await,
+            Self::Response(res) => res.write_to(w).await,
+        }
+    }
 }
 
 impl From<Request> for Message {
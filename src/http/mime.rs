@@ -0,0 +1,65 @@
+use std::path::Path;
+
+pub const DEFAULT_TYPE: &str = "application/octet-stream";
+
+pub const DEFAULT_COMPRESSIBLE_TYPES: &[&str] = &[
+    "text/*", "application/json", "application/javascript", "application/xml",
+    "application/wasm", "application/manifest+json", "image/svg+xml",
+];
+
+pub const DEFAULT_SKIP_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "avif", "ico",
+    "mp3", "mp4", "m4a", "ogg", "webm", "mkv",
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "br", "7z", "rar",
+    "woff", "woff2", "pdf",
+];
+
+pub fn from_extension(ext: &str) -> Option<&'static str> {
+    Some(match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "application/javascript; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "svg" => "image/svg+xml",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    })
+}
+
+pub fn for_path(path: &Path) -> &'static str {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(from_extension)
+        .unwrap_or(DEFAULT_TYPE)
+}
+
+pub fn matches(pattern: &str, mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or("").trim();
+
+    match pattern.strip_suffix("/*") {
+        Some(kind) => essence.split('/').next().is_some_and(|k| k.eq_ignore_ascii_case(kind)),
+        None => essence.eq_ignore_ascii_case(pattern),
+    }
+}
@@ -1,30 +1,42 @@
 
-use tokio::io::AsyncBufRead;
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 
 use anyhow::Result;
 
+pub mod body;
+pub mod compress;
+pub mod conditional;
+pub mod date;
 pub mod message;
+pub mod mime;
+pub mod negotiate;
+pub mod range;
 pub mod reader;
 pub mod request;
 pub mod response;
 pub mod stream;
+pub mod timeout;
+pub mod uri;
 pub mod writer;
 
 pub trait Serialize {
     fn serialize(&self) -> Result<Vec<u8>>;
+
+    async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<()> {
+        w.write_all(&self.serialize()?).await?;
+
+        Ok(())
+    }
 }
 
 pub trait Deserialize<R: AsyncBufRead>
 where
     Self: Sized
 {
+    #[cfg_attr(not(feature = "record"), allow(dead_code))]
     async fn deserialize(reader: &mut R) -> Result<Self>;
 }
 
 pub trait AsyncWriteObj<T: Serialize> {
     async fn write_obj(&mut self, obj: &T) -> Result<()>;
 }
-
-pub trait AsyncReadObj<R: AsyncBufRead, T: Deserialize<R>> {
-    async fn read_obj(&mut self) -> Result<T>;
-}
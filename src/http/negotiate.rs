@@ -0,0 +1,116 @@
+use crate::http::mime;
+
+pub fn qvalues(header: &str) -> impl Iterator<Item = (&str, f32)> {
+    header.split(',').map(|item| {
+        let mut params = item.split(';').map(str::trim);
+        let name = params.next().unwrap_or("");
+
+        let q = params
+            .filter_map(|p| p.strip_prefix("q="))
+            .filter_map(|q| q.parse::<f32>().ok())
+            .next()
+            .unwrap_or(1.0);
+
+        (name, q)
+    })
+}
+
+pub fn languages(accept_language: &str) -> Vec<String> {
+    let mut ranked: Vec<(String, f32)> = qvalues(accept_language)
+        .filter(|&(name, q)| !name.is_empty() && name != "*" && q > 0.0)
+        .map(|(name, q)| (name.to_ascii_lowercase(), q))
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut languages: Vec<String> = vec![];
+
+    for (name, _) in ranked {
+        let primary = name.split('-').next().unwrap_or("").to_string();
+
+        for candidate in [name, primary] {
+            if !languages.contains(&candidate) {
+                languages.push(candidate);
+            }
+        }
+    }
+
+    languages
+}
+
+pub fn wants_digest(want_digest: &str, algorithm: &str) -> bool {
+    qvalues(want_digest).any(|(name, q)| name.eq_ignore_ascii_case(algorithm) && q > 0.0)
+}
+
+pub fn accepts_chunked(te: &str) -> bool {
+    let mut chunked = None;
+    let mut identity = false;
+
+    for (name, q) in qvalues(te) {
+        if name.eq_ignore_ascii_case("chunked") {
+            chunked = Some(q > 0.0);
+        } else if name.eq_ignore_ascii_case("identity") {
+            identity = q > 0.0;
+        }
+    }
+
+    chunked.unwrap_or(!identity)
+}
+
+pub fn variant(path: &str, language: &str) -> String {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+    match name.split_once('.') {
+        Some((stem, ext)) => format!("{dir}/{stem}.{language}.{ext}"),
+        None => format!("{dir}/{name}.{language}"),
+    }
+}
+
+pub fn media_type<'a>(accept: &str, offered: &[&'a str]) -> Option<&'a str> {
+    let ranges: Vec<(&str, f32)> = qvalues(accept)
+        .filter(|(range, _)| !range.is_empty())
+        .collect();
+
+    let quality = |offer: &str| {
+        ranges.iter()
+            .filter(|(range, _)| *range == "*/*" || mime::matches(range, offer))
+            .max_by_key(|(range, _)| if *range == "*/*" { 0 } else if range.ends_with("/*") { 1 } else { 2 })
+            .map(|(_, q)| *q)
+    };
+
+    let mut best = None;
+
+    for &offer in offered {
+        if let Some(q) = quality(offer).filter(|&q| q > 0.0) {
+            if best.is_none_or(|(_, b)| q > b) {
+                best = Some((offer, q));
+            }
+        }
+    }
+
+    best.map(|(offer, _)| offer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_qvalues() {
+        let parsed: Vec<_> = qvalues("gzip;q=0.5, br, identity; q=0").collect();
+        assert_eq!(parsed, [("gzip", 0.5), ("br", 1.0), ("identity", 0.0)]);
+    }
+
+    #[test]
+    fn ignores_malformed_qvalues() {
+        let parsed: Vec<_> = qvalues("en;q=high;level=1").collect();
+        assert_eq!(parsed, [("en", 1.0)]);
+    }
+
+    #[test]
+    fn refuses_chunked_with_zero_quality() {
+        assert!(accepts_chunked("trailers"));
+        assert!(!accepts_chunked("chunked;q=0"));
+        assert!(!accepts_chunked("identity"));
+    }
+}
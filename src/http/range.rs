@@ -0,0 +1,97 @@
+use std::fmt::{self, Display};
+use std::error::Error;
+
+pub enum ByteRange {
+    FromTo(u64, u64),
+    From(u64),
+    Suffix(u64),
+}
+
+impl ByteRange {
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            Self::FromTo(start, end) if start < len => Some((start, end.min(len - 1))),
+            Self::From(start) if start < len => Some((start, len - 1)),
+            Self::Suffix(n) if n > 0 && len > 0 => Some((len - n.min(len), len - 1)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RangeParseError {
+    UnsupportedUnit,
+    Malformed,
+}
+
+impl Display for RangeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::UnsupportedUnit => "the range unit is not bytes",
+            Self::Malformed => "the range is malformed",
+        })
+    }
+}
+
+impl Error for RangeParseError {}
+
+impl std::str::FromStr for ByteRange {
+    type Err = RangeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (unit, spec) = s.split_once('=').ok_or(RangeParseError::Malformed)?;
+
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return Err(RangeParseError::UnsupportedUnit);
+        }
+
+        let (start, end) = spec.trim().split_once('-').ok_or(RangeParseError::Malformed)?;
+        let parse = |n: &str| n.parse::<u64>().map_err(|_| RangeParseError::Malformed);
+
+        match (start, end) {
+            ("", suffix) => Ok(Self::Suffix(parse(suffix)?)),
+            (start, "") => Ok(Self::From(parse(start)?)),
+            (start, end) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+
+                if start > end {
+                    return Err(RangeParseError::Malformed);
+                }
+
+                Ok(Self::FromTo(start, end))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert!(matches!("bytes=0-99".parse(), Ok(ByteRange::FromTo(0, 99))));
+        assert!(matches!("bytes=100-".parse(), Ok(ByteRange::From(100))));
+        assert!(matches!("Bytes = -50".parse(), Ok(ByteRange::Suffix(50))));
+    }
+
+    #[test]
+    fn rejects_malformed_ranges() {
+        assert!(matches!("items=0-1".parse::<ByteRange>(), Err(RangeParseError::UnsupportedUnit)));
+        assert!(matches!("bytes=5-1".parse::<ByteRange>(), Err(RangeParseError::Malformed)));
+        assert!(matches!("bytes=a-b".parse::<ByteRange>(), Err(RangeParseError::Malformed)));
+        assert!(matches!("bytes=-".parse::<ByteRange>(), Err(RangeParseError::Malformed)));
+        assert!(matches!("bytes 0-1".parse::<ByteRange>(), Err(RangeParseError::Malformed)));
+    }
+
+    #[test]
+    fn resolves_against_length() {
+        assert_eq!(ByteRange::FromTo(0, 99).resolve(50), Some((0, 49)));
+        assert_eq!(ByteRange::FromTo(50, 99).resolve(50), None);
+        assert_eq!(ByteRange::From(10).resolve(50), Some((10, 49)));
+        assert_eq!(ByteRange::Suffix(10).resolve(50), Some((40, 49)));
+        assert_eq!(ByteRange::Suffix(100).resolve(50), Some((0, 49)));
+        assert_eq!(ByteRange::Suffix(0).resolve(50), None);
+        assert_eq!(ByteRange::Suffix(10).resolve(0), None);
+    }
+}
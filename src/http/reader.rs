@@ -1,24 +1,48 @@
-use tokio::io::AsyncBufRead;
+use std::io;
 
-use crate::http::AsyncReadObj;
-use crate::http::message::Message;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
-use anyhow::Result;
+use crate::http::body::{Chunked, RequestBody};
+use crate::http::message::{Limits, Message, MessageParseError};
+use crate::http::request::Request;
 
-use super::Deserialize;
+use anyhow::Result;
 
 pub struct HttpReader<R: AsyncBufRead> {
     reader: R,
+    limits: Limits,
 }
 
 impl<R: AsyncBufRead> HttpReader<R> {
-    pub fn new(reader: R) -> Self {
-        Self { reader }
+    pub fn with_limits(reader: R, limits: Limits) -> Self {
+        Self { reader, limits }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
     }
 }
 
-impl<R: AsyncBufRead + Unpin> AsyncReadObj<R, Message> for HttpReader<R> {
-    async fn read_obj(&mut self) -> Result<Message> {
-        Message::deserialize(&mut self.reader).await
+impl<R: AsyncBufRead + Unpin> HttpReader<R> {
+    pub async fn ready(&mut self) -> io::Result<bool> {
+        Ok(!self.reader.fill_buf().await?.is_empty())
+    }
+
+    pub async fn next_request_streaming(&mut self) -> Result<Option<(Request, RequestBody<'_, R>)>> {
+        match Message::read_head_with_limits(&mut self.reader, &self.limits).await {
+            Ok(Message::Request(req)) => {
+                let Limits { body_chunk_size, body_timeout, max_body, max_header_size, .. } = self.limits;
+
+                let body = match req.body_length()? {
+                    Some(length) => RequestBody::new(&mut self.reader, length, body_chunk_size, body_timeout),
+                    None => RequestBody::chunked(Chunked::new(&mut self.reader, max_body, max_header_size), body_chunk_size, body_timeout),
+                };
+
+                Ok(Some((req, body)))
+            },
+            Ok(Message::Response(_)) => Err(MessageParseError::UnexpectedResponse.into()),
+            Err(e) if matches!(e.downcast_ref(), Some(MessageParseError::ConnectionClosed)) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
 }
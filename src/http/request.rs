@@ -1,46 +1,89 @@
 use std::collections::HashMap;
 use std::fmt::Write;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use anyhow::Result;
 
-use crate::http::message::{Method, Version};
+use crate::http::message::{MessageParseError, Method, Version};
+use crate::http::uri::{self, Form, Target};
 use crate::http::Serialize;
 
 pub struct Request {
     pub method: Method,
     pub resource: String,
+    pub target: Target,
     pub version: Version,
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
 }
 
 impl Request {
-    pub async fn new<R>(method: Method, resource: &str, version: Version, headers: HashMap<String, String>, body: &mut R) -> Result<Self>
-    where
-        R: AsyncRead + Unpin
-    {
-        let mut bodyvec = vec![];
-
-        if let Some(length) = headers.get("Content-Length").map(|l| l.parse::<usize>()) {
-            let length = length?;
-
-            bodyvec = vec![0u8; length];
-            body.read_exact(&mut bodyvec).await?;
-        }
-
+    pub fn new(method: Method, resource: &str, version: Version, headers: HashMap<String, String>) -> Result<Self> {
         Ok(Self {
             method,
             resource: resource.to_string(),
+            target: resource.parse()?,
             version,
             headers,
-            body: bodyvec,
+            body: vec![],
         })
     }
+
+    pub fn path(&self) -> String {
+        match self.target.form {
+            Form::Origin | Form::Absolute => uri::normalize(&String::from_utf8_lossy(&uri::percent_decode(&self.target.path))),
+            Form::Authority | Form::Asterisk => self.target.path.clone(),
+        }
+    }
+
+    pub fn content_length(&self) -> Result<u64> {
+        match self.header::<String>("Content-Length") {
+            Some(length) => Ok(length.parse().map_err(|_| MessageParseError::InvalidContentLength)?),
+            None => Ok(0),
+        }
+    }
+
+    pub fn body_length(&self) -> Result<Option<u64>> {
+        match (self.header::<String>("Transfer-Encoding"), self.header::<String>("Content-Length")) {
+            (Some(_), Some(_)) => Err(MessageParseError::ConflictingLength.into()),
+            (Some(te), None) if te.trim().eq_ignore_ascii_case("chunked") => Ok(None),
+            (Some(_), None) => Err(MessageParseError::UnsupportedTransferEncoding.into()),
+            (None, _) => Ok(Some(self.content_length()?)),
+        }
+    }
+
+    pub async fn read_body<R>(&mut self, reader: &mut R) -> Result<()>
+    where
+        R: AsyncRead + Unpin
+    {
+        let length = self.body_length()?.ok_or(MessageParseError::UnsupportedTransferEncoding)?;
+        let mut body = vec![0u8; length as usize];
+        reader.read_exact(&mut body).await?;
+        self.body = body;
+
+        Ok(())
+    }
+
+    pub fn header<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.headers.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .and_then(|(_, v)| v.parse().ok())
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header::<String>("Connection").unwrap_or_default();
+        let has = |token: &str| connection.split(',').any(|t| t.trim().eq_ignore_ascii_case(token));
+
+        match self.version {
+            Version::Http10 => has("keep-alive") && !has("close"),
+            _ => !has("close"),
+        }
+    }
 }
 
-impl Serialize for Request {
-    fn serialize(&self) -> Result<Vec<u8>> {
+impl Request {
+    pub fn head(&self) -> Result<String> {
         let mut out = String::new();
 
         write!(out, "{} {} {}\r\n", self.method, self.resource, self.version)?;
@@ -51,11 +94,22 @@ impl Serialize for Request {
 
         write!(out, "\r\n")?;
 
-        let mut out: Vec<u8> = out.bytes().collect();
-        let mut data = self.body.clone();
+        Ok(out)
+    }
+}
 
-        out.append(&mut data);
+impl Serialize for Request {
+    fn serialize(&self) -> Result<Vec<u8>> {
+        let mut out: Vec<u8> = self.head()?.into_bytes();
+        out.extend_from_slice(&self.body);
 
         Ok(out)
     }
+
+    async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<()> {
+        w.write_all(self.head()?.as_bytes()).await?;
+        w.write_all(&self.body).await?;
+
+        Ok(())
+    }
 }
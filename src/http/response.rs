@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 use std::fmt::Write as _;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use std::io::SeekFrom;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use tokio::fs::File;
 
 use anyhow::Result;
 
+use crate::http::compress;
 use crate::http::message::Version;
+use crate::http::mime;
 use crate::http::Serialize;
 
 pub struct Response {
@@ -14,6 +18,7 @@ pub struct Response {
     pub message: String,
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
+    pub stream: Option<Box<dyn AsyncRead + Send + Sync + Unpin>>,
 }
 
 impl Response {
@@ -36,9 +41,49 @@ impl Response {
             message: message.into(),
             headers,
             body: bodyvec,
+            stream: None,
         })
     }
 
+    pub fn from_bytes(version: Version, code: u32, body: Vec<u8>) -> Self {
+        let mut headers = HashMap::new();
+
+        if !(100..200).contains(&code) && code != 204 && code != 304 {
+            headers.insert("Content-Length".into(), body.len().to_string());
+        }
+
+        Self {
+            version,
+            code,
+            message: Self::message(code).unwrap_or("Unknown").into(),
+            headers,
+            body,
+            stream: None,
+        }
+    }
+
+    pub fn redirect(version: Version, code: u32, location: &str, headers: &[(&str, &str)]) -> Self {
+        let mut response = Self::from_bytes(version, code, vec![]);
+        response.headers.insert("Location".into(), location.into());
+
+        for (name, value) in headers {
+            response.headers.insert(name.to_string(), value.to_string());
+        }
+
+        response
+    }
+
+    pub fn not_modified(version: Version, etag: &str, cache_control: Option<&str>) -> Self {
+        let mut response = Self::from_bytes(version, 304, vec![]);
+        response.headers.insert("ETag".into(), etag.into());
+
+        if let Some(cache_control) = cache_control {
+            response.headers.insert("Cache-Control".into(), cache_control.into());
+        }
+
+        response
+    }
+
     pub fn message(code: u32) -> Option<&'static str> {
         match code {
             100 => Some("Continue"),
@@ -80,10 +125,10 @@ impl Response {
             413 => Some("Payload Too Large"),
             414 => Some("URI Too Long"),
             415 => Some("Unsupported Media Type"),
-            416 => Some("Range Not Satisfyable"),
+            416 => Some("Range Not Satisfiable"),
             417 => Some("Expectation Failed"),
             418 => Some("I'm a teapot"),
-            421 => Some("Misdirect Request"),
+            421 => Some("Misdirected Request"),
             422 => Some("Unprocessable Content"),
             423 => Some("Locked"),
             424 => Some("Failed Dependency"),
@@ -109,24 +154,107 @@ impl Response {
         }
     }
 
-    pub async fn serve_file_with_code(version: Version, code: u32, file: &mut File) -> Result<Self> {
+    pub async fn serve_file_with_code(version: Version, code: u32, file: &mut File, path: &Path) -> Result<Self> {
         let headers = HashMap::from([
-            ("Content-Length".into(), file.metadata().await?.len().to_string())
+            ("Content-Length".into(), file.metadata().await?.len().to_string()),
+            ("Content-Type".into(), mime::for_path(path).into()),
         ]);
 
         Self::new(version, code, Self::message(code).unwrap_or("Unknown"), headers, file).await
     }
 
-    pub async fn serve_file(version: Version, file: &mut File) -> Result<Self> {
-        Self::serve_file_with_code(version, 200, file).await
+    pub fn gzip(&mut self) -> Result<()> {
+        let body = compress::gzip(&self.body)?;
+        self.encoded("gzip", body);
+
+        Ok(())
     }
-}
 
-impl Serialize for Response {
-    fn serialize(&self) -> Result<Vec<u8>> {
+    pub fn encoded(&mut self, coding: &str, body: Vec<u8>) {
+        self.body = body;
+        self.stream = None;
+
+        self.headers.insert("Content-Encoding".into(), coding.into());
+        self.headers.insert("Content-Length".into(), self.body.len().to_string());
+        self.headers.remove("Accept-Ranges");
+    }
+
+    pub fn disable_ranges(&mut self) {
+        self.headers.insert("Accept-Ranges".into(), "none".into());
+    }
+
+    pub async fn serve_file(version: Version, file: &mut File, path: &Path) -> Result<Self> {
+        let mut response = Self::serve_file_with_code(version, 200, file, path).await?;
+        response.headers.insert("Accept-Ranges".into(), "bytes".into());
+
+        Ok(response)
+    }
+
+    pub fn serve_bytes(version: Version, data: Vec<u8>) -> Self {
+        let mut response = Self::from_bytes(version, 200, data);
+        response.headers.insert("Accept-Ranges".into(), "bytes".into());
+
+        response
+    }
+
+    pub fn serve_reader<R>(version: Version, code: u32, content_type: Option<&str>, length: u64, reader: R) -> Self
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static
+    {
+        let mut response = Self::from_bytes(version, code, vec![]);
+
+        response.headers.insert("Content-Length".into(), length.to_string());
+        response.stream = Some(Box::new(reader.take(length)));
+
+        if let Some(content_type) = content_type {
+            response.headers.insert("Content-Type".into(), content_type.into());
+        }
+
+        response
+    }
+
+    pub fn serve_chunked<R>(version: Version, code: u32, reader: R) -> Self
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static
+    {
+        let mut response = Self::from_bytes(version, code, vec![]);
+
+        response.headers.remove("Content-Length");
+        response.headers.insert("Transfer-Encoding".into(), "chunked".into());
+        response.stream = Some(Box::new(reader));
+
+        response
+    }
+
+    pub async fn serve_file_streaming(version: Version, file: File) -> Result<Self> {
+        let length = file.metadata().await?.len();
+
+        let mut response = Self::serve_reader(version, 200, None, length, file);
+        response.headers.insert("Accept-Ranges".into(), "bytes".into());
+
+        Ok(response)
+    }
+
+    pub async fn serve_file_range(version: Version, mut file: File, start: u64, end: u64, total: u64) -> Result<Self> {
+        file.seek(SeekFrom::Start(start)).await?;
+
+        let mut response = Self::serve_reader(version, 206, None, end - start + 1, file);
+
+        response.headers.insert("Content-Range".into(), format!("bytes {start}-{end}/{total}"));
+        response.headers.insert("Accept-Ranges".into(), "bytes".into());
+
+        Ok(response)
+    }
+
+    pub fn discard_body(&mut self) {
+        self.body.clear();
+        self.stream = None;
+    }
+
+    pub fn head(&self) -> Result<String> {
         let mut out = String::new();
 
-        write!(out, "{} {} {}\r\n", self.version, self.code.to_string(), self.message)?;
+        write!(out, "{} {} {}\r\n", self.version, self.code, self.message)?;
 
         for header in &self.headers {
             write!(out, "{}: {}\r\n", header.0, header.1)?;
@@ -134,11 +262,35 @@ impl Serialize for Response {
 
         write!(out, "\r\n")?;
 
-        let mut out: Vec<u8> = out.bytes().collect();
-        let mut data = self.body.clone();
+        Ok(out)
+    }
+
+    pub fn chunked(&self) -> bool {
+        self.headers.iter()
+            .any(|(k, v)| k.eq_ignore_ascii_case("Transfer-Encoding") && v.to_ascii_lowercase().contains("chunked"))
+    }
+
+    pub fn framed(&self) -> bool {
+        matches!(self.code, 100..=199 | 204 | 304) || self.chunked() || self.content_length().is_some()
+    }
 
-        out.append(&mut data);
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers.get("Content-Length").and_then(|l| l.parse().ok())
+    }
+}
+
+impl Serialize for Response {
+    fn serialize(&self) -> Result<Vec<u8>> {
+        let mut out: Vec<u8> = self.head()?.into_bytes();
+        out.extend_from_slice(&self.body);
 
         Ok(out)
     }
+
+    async fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<()> {
+        w.write_all(self.head()?.as_bytes()).await?;
+        w.write_all(&self.body).await?;
+
+        Ok(())
+    }
 }
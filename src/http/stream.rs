@@ -1,5 +1,6 @@
 use tokio::io::{self, ReadHalf, WriteHalf, AsyncRead, AsyncWrite, BufReader};
 
+use crate::http::message::Limits;
 use crate::http::reader::HttpReader;
 use crate::http::writer::HttpWriter;
 
@@ -9,11 +10,11 @@ pub struct HttpStream<S: AsyncRead + AsyncWrite> {
 }
 
 impl<S: AsyncRead + AsyncWrite> HttpStream<S> {
-    pub fn new(stream: S) -> Self {
+    pub fn with_limits(stream: S, limits: Limits) -> Self {
         let (reader, writer) = io::split(stream);
         let reader = BufReader::new(reader);
 
-        let reader = HttpReader::new(reader);
+        let reader = HttpReader::with_limits(reader, limits);
         let writer = HttpWriter::new(writer);
 
         Self { reader, writer }
@@ -0,0 +1,102 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{self, Instant, Sleep};
+
+struct Deadline {
+    timeout: Option<Duration>,
+    sleep: Pin<Box<Sleep>>,
+    armed: bool,
+    message: &'static str,
+}
+
+impl Deadline {
+    fn new(timeout: Option<Duration>, message: &'static str) -> Self {
+        Self { timeout, sleep: Box::pin(time::sleep(Duration::ZERO)), armed: false, message }
+    }
+
+    fn poll_expired<T>(&mut self, cx: &mut Context<'_>, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        let Some(timeout) = self.timeout else {
+            return poll;
+        };
+
+        if poll.is_ready() {
+            self.armed = false;
+            return poll;
+        }
+
+        if !self.armed {
+            self.sleep.as_mut().reset(Instant::now() + timeout);
+            self.armed = true;
+        }
+
+        match self.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, self.message))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub struct IdleTimeout<S> {
+    inner: S,
+    read: Deadline,
+    write: Deadline,
+}
+
+impl<S> IdleTimeout<S> {
+    pub fn new(inner: S, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            read: Deadline::new(timeout, "connection idle timeout"),
+            write: Deadline::new(timeout, "connection idle timeout"),
+        }
+    }
+
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write = Deadline::new(timeout, "connection write timeout");
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for IdleTimeout<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.read.poll_expired(cx, poll)
+    }
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncBufRead for IdleTimeout<S> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_fill_buf(cx);
+        this.read.poll_expired(cx, poll)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for IdleTimeout<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.write.poll_expired(cx, poll)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.inner).poll_flush(cx);
+        self.write.poll_expired(cx, poll)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,255 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum TargetParseError {
+    Empty,
+    Scheme,
+    Authority,
+}
+
+impl Display for TargetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Empty => "the request target is empty",
+            Self::Scheme => "the request target has an invalid scheme",
+            Self::Authority => "the request target has an invalid authority",
+        })
+    }
+}
+
+impl Error for TargetParseError {}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Form {
+    Origin,
+    Absolute,
+    Authority,
+    Asterisk,
+}
+
+#[derive(Clone)]
+pub struct Target {
+    pub form: Form,
+    pub scheme: Option<String>,
+    pub authority: Option<String>,
+    pub path: String,
+    pub query: Option<String>,
+}
+
+impl Target {
+    fn origin(form: Form, scheme: Option<String>, authority: Option<String>, rest: &str) -> Self {
+        let rest = rest.split('#').next().unwrap_or("");
+
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query.into())),
+            None => (rest, None),
+        };
+
+        let path = if path.is_empty() { "/".into() } else { path.into() };
+
+        Self { form, scheme, authority, path, query }
+    }
+}
+
+impl FromStr for Target {
+    type Err = TargetParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(TargetParseError::Empty);
+        }
+
+        if s == "*" {
+            return Ok(Self { form: Form::Asterisk, scheme: None, authority: None, path: "*".into(), query: None });
+        }
+
+        if s.starts_with('/') {
+            return Ok(Self::origin(Form::Origin, None, None, s));
+        }
+
+        if let Some((scheme, rest)) = s.split_once("://") {
+            if scheme.is_empty() || !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            {
+                return Err(TargetParseError::Scheme);
+            }
+
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let (authority, rest) = rest.split_at(end);
+
+            if authority.is_empty() {
+                return Err(TargetParseError::Authority);
+            }
+
+            return Ok(Self::origin(Form::Absolute, Some(scheme.to_ascii_lowercase()), Some(authority.into()), rest));
+        }
+
+        match s.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && !s.contains(['/', '?', '#']) && port.parse::<u16>().is_ok() =>
+                Ok(Self { form: Form::Authority, scheme: None, authority: Some(s.into()), path: String::new(), query: None }),
+            _ => Err(TargetParseError::Authority),
+        }
+    }
+}
+
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+pub fn portable_segment(segment: &str) -> bool {
+    let stem = segment.split('.').next().unwrap_or("").trim_end().to_ascii_lowercase();
+
+    !segment.ends_with(['.', ' ']) && !RESERVED_NAMES.contains(&stem.as_str())
+}
+
+pub fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            },
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            },
+        }
+    }
+
+    out
+}
+
+pub fn climbs(path: &str) -> bool {
+    let decoded = String::from_utf8_lossy(&percent_decode(path)).into_owned();
+    let mut depth = 0usize;
+
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => (),
+            ".." if depth == 0 => return true,
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+    }
+
+    false
+}
+
+pub fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = vec![];
+    let mut directory = false;
+
+    for segment in path.split('/') {
+        directory = matches!(segment, "" | "." | "..");
+
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop();
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+
+    if directory && !segments.is_empty() {
+        normalized.push('/');
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_origin_form() {
+        let target: Target = "/docs/index.html?lang=en#top".parse().unwrap();
+
+        assert!(target.form == Form::Origin);
+        assert_eq!(target.path, "/docs/index.html");
+        assert_eq!(target.query.as_deref(), Some("lang=en"));
+        assert!(target.scheme.is_none() && target.authority.is_none());
+    }
+
+    #[test]
+    fn parses_absolute_form() {
+        let target: Target = "HTTP://example.com:8080?x=1".parse().unwrap();
+
+        assert!(target.form == Form::Absolute);
+        assert_eq!(target.scheme.as_deref(), Some("http"));
+        assert_eq!(target.authority.as_deref(), Some("example.com:8080"));
+        assert_eq!(target.path, "/");
+        assert_eq!(target.query.as_deref(), Some("x=1"));
+    }
+
+    #[test]
+    fn parses_authority_form() {
+        let target: Target = "example.com:443".parse().unwrap();
+
+        assert!(target.form == Form::Authority);
+        assert_eq!(target.authority.as_deref(), Some("example.com:443"));
+        assert!(matches!("example.com".parse::<Target>(), Err(TargetParseError::Authority)));
+        assert!(matches!(":443".parse::<Target>(), Err(TargetParseError::Authority)));
+    }
+
+    #[test]
+    fn parses_asterisk_form() {
+        let target: Target = "*".parse().unwrap();
+
+        assert!(target.form == Form::Asterisk);
+        assert_eq!(target.path, "*");
+    }
+
+    #[test]
+    fn rejects_malformed_targets() {
+        assert!(matches!("".parse::<Target>(), Err(TargetParseError::Empty)));
+        assert!(matches!("1http://example.com/".parse::<Target>(), Err(TargetParseError::Scheme)));
+        assert!(matches!("http:///path".parse::<Target>(), Err(TargetParseError::Authority)));
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize("/a/./b/../c"), "/a/c");
+        assert_eq!(normalize("/a/b/.."), "/a/");
+        assert_eq!(normalize("/../.."), "/");
+        assert_eq!(normalize("//a//b/"), "/a/b/");
+    }
+
+    #[test]
+    fn detects_climbing_paths() {
+        assert!(climbs("/../etc/passwd"));
+        assert!(climbs("/a/%2e%2e/%2E%2E/b"));
+        assert!(climbs("/a\\..\\..\\b"));
+        assert!(!climbs("/a/../b"));
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("/a%20b%2fc"), b"/a b/c");
+        assert_eq!(percent_decode("100%"), b"100%");
+        assert_eq!(percent_decode("%zz"), b"%zz");
+    }
+
+    #[test]
+    fn rejects_unportable_segments() {
+        assert!(portable_segment("index.html"));
+        assert!(!portable_segment("CON.txt"));
+        assert!(!portable_segment("trailing."));
+        assert!(!portable_segment("trailing "));
+    }
+}
@@ -1,24 +1,107 @@
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use anyhow::Result;
 
 use super::AsyncWriteObj;
 use super::Serialize;
+use super::response::Response;
+
+const CHUNK_SIZE: usize = 16384;
+
+struct Counted<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for Counted<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(n)) = poll {
+            self.count += n as u64;
+        }
+
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
 
 pub struct HttpWriter<W: AsyncWrite> {
-    writer: W,
+    writer: Counted<W>,
 }
 
 impl<W: AsyncWrite> HttpWriter<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self { writer: Counted { inner: writer, count: 0 } }
+    }
+
+    pub fn written(&self) -> u64 {
+        self.writer.count
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> HttpWriter<W> {
+    pub async fn write_response(&mut self, response: &mut Response) -> Result<()> {
+        self.write_obj(response).await?;
+
+        let chunked = response.chunked();
+
+        if let Some(stream) = response.stream.as_mut() {
+            let mut chunk = vec![0u8; CHUNK_SIZE];
+            let mut written = 0u64;
+
+            loop {
+                let n = stream.read(&mut chunk).await?;
+
+                if n == 0 {
+                    break;
+                }
+
+                if chunked {
+                    self.writer.write_all(format!("{n:x}\r\n").as_bytes()).await?;
+                    self.writer.write_all(&chunk[..n]).await?;
+                    self.writer.write_all(b"\r\n").await?;
+                } else {
+                    self.writer.write_all(&chunk[..n]).await?;
+                }
+
+                written += n as u64;
+            }
+
+            if chunked {
+                self.writer.write_all(b"0\r\n\r\n").await?;
+            }
+
+            self.writer.flush().await?;
+
+            if response.content_length().is_some_and(|length| written < length) {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "response body ended before its Content-Length").into());
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl<W: AsyncWrite + Unpin, T: Serialize> AsyncWriteObj<T> for HttpWriter<W> {
     async fn write_obj(&mut self, obj: &T) -> Result<()> {
-        let raw = obj.serialize()?;
-        self.writer.write(&raw).await?;
+        obj.write_to(&mut self.writer).await?;
+        self.writer.flush().await?;
 
         Ok(())
     }
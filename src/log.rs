@@ -0,0 +1,102 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::http::date::DateTime;
+
+pub struct LogRecord {
+    pub remote: Option<SocketAddr>,
+    pub time: SystemTime,
+    pub method: String,
+    pub resource: String,
+    pub version: String,
+    pub status: u32,
+    pub bytes: usize,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub duration: Duration,
+}
+
+pub trait AccessLogger: Send + Sync {
+    fn log(&self, record: &LogRecord);
+}
+
+pub struct StdoutLogger;
+
+impl AccessLogger for StdoutLogger {
+    fn log(&self, record: &LogRecord) {
+        let time = DateTime::from(record.time);
+
+        println!("{} - - [{:02}/{}/{}:{:02}:{:02}:{:02} +0000] \"{} {} {}\" {} {}",
+            record.remote.map(|a| a.ip().to_string()).unwrap_or("-".into()),
+            time.day, time.month_name(), time.year, time.hour, time.minute, time.second,
+            record.method, record.resource, record.version,
+            record.status, record.bytes);
+    }
+}
+
+pub struct SlowLogger {
+    inner: Box<dyn AccessLogger>,
+    threshold: Duration,
+}
+
+impl SlowLogger {
+    pub fn new(inner: Box<dyn AccessLogger>, threshold: Duration) -> Self {
+        Self { inner, threshold }
+    }
+}
+
+impl AccessLogger for SlowLogger {
+    fn log(&self, record: &LogRecord) {
+        if record.duration >= self.threshold {
+            self.inner.log(record);
+        }
+    }
+}
+
+pub struct FileLogger {
+    lines: Sender<String>,
+}
+
+impl FileLogger {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, rx) = mpsc::channel::<String>();
+
+        thread::spawn(move || {
+            for line in rx {
+                if let Err(e) = writeln!(file, "{line}") {
+                    eprintln!("failed to write access log: {e}");
+                }
+            }
+        });
+
+        Ok(Self { lines })
+    }
+}
+
+impl AccessLogger for FileLogger {
+    fn log(&self, record: &LogRecord) {
+        let time = DateTime::from(record.time);
+
+        let line = serde_json::json!({
+            "remote": record.remote.map(|a| a.ip().to_string()),
+            "time": format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                time.year, time.month, time.day, time.hour, time.minute, time.second),
+            "method": record.method,
+            "resource": record.resource,
+            "version": record.version,
+            "status": record.status,
+            "bytes": record.bytes,
+            "referer": record.referer,
+            "user_agent": record.user_agent,
+            "duration_ms": record.duration.as_secs_f64() * 1000.0,
+        });
+
+        let _ = self.lines.send(line.to_string());
+    }
+}
@@ -1,82 +1,461 @@
 use std::fs;
+use std::net::SocketAddr;
 use std::path::{PathBuf, Path};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use std::io;
+use std::num::ParseIntError;
+use std::string::FromUtf8Error;
 
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::fs::File;
 use tokio::net;
-
-use serde::Deserialize;
+use tokio::signal;
+use tokio::sync::Semaphore;
 
 use anyhow::Result;
+use socket2::{SockRef, TcpKeepalive};
 
+mod autoindex;
+mod config;
+mod etag;
+mod filecache;
 mod http;
+mod log;
+mod pattern;
+mod ratelimit;
+#[cfg(feature = "record")]
+mod record;
+mod tls;
+mod writable;
 
-use http::message::{Message, MessageParseError, Version};
+use config::{CatchAllConfig, Config, EtagMode, SlashPolicy, Strategy};
+use etag::ContentHashes;
+use filecache::FileCache;
+use http::body::RequestBody;
+use http::compress;
+use http::conditional::{self, Precondition};
+use http::range::ByteRange;
+use http::message::{MessageParseError, Method, Version, VersionParseError};
+use http::negotiate;
 use http::response::Response;
 use http::request::Request;
 use http::stream::HttpStream;
-use http::{AsyncReadObj, AsyncWriteObj};
+use http::uri::{self, Form, TargetParseError};
+use http::timeout::IdleTimeout;
+use http::AsyncWriteObj;
+use log::{AccessLogger, FileLogger, LogRecord, SlowLogger, StdoutLogger};
+use ratelimit::{ConnectionGuard, ConnectionLimiter, TokenBucket};
+use tls::TlsInfo;
+
+struct Active<'a>(&'a AtomicUsize);
 
-#[derive(Deserialize, Clone)]
-struct HttpConfig {
-    port: Option<u16>,
-    address: Option<String>,
+impl<'a> Active<'a> {
+    fn enter(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
 }
 
-#[derive(Deserialize, Clone)]
-struct HttpsConfig {
-    port: Option<u16>,
-    address: Option<String>,
-    key: PathBuf,
-    cert: PathBuf,
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
-#[derive(Deserialize, Clone)]
-struct Config {
-    root: String,
-    host: String,
-    http: Option<HttpConfig>,
-    https: Option<HttpsConfig>,
+struct Server {
+    config: Config,
+    logger: Box<dyn AccessLogger>,
+    requests: AtomicU64,
+    active: AtomicUsize,
+    bytes_sent: AtomicU64,
+    hashes: ContentHashes,
+    cache: Option<FileCache>,
+    compressed: Option<FileCache>,
+    in_flight: Option<Semaphore>,
+    draining: AtomicBool,
+    accept_limiter: Option<TokenBucket>,
+    per_ip: Option<ConnectionLimiter>,
+    #[cfg(feature = "record")]
+    recorder: Option<record::Recorder>,
 }
 
+impl Server {
+    fn new(config: Config) -> Result<Self> {
+        let logger: Box<dyn AccessLogger> = match &config.access_log {
+            Some(path) => Box::new(FileLogger::open(path)?),
+            None => Box::new(StdoutLogger),
+        };
+
+        let logger: Box<dyn AccessLogger> = match config.slow_request_threshold {
+            Some(ms) => Box::new(SlowLogger::new(logger, Duration::from_millis(ms))),
+            None => logger,
+        };
+
+        let accept_limiter = config.max_connections_per_second
+            .filter(|&rate| rate > 0)
+            .map(|rate| TokenBucket::new(rate as f64, rate as f64));
+
+        let cache = config.cache.as_ref()
+            .map(|c| FileCache::new(c.max_size.unwrap_or(16 << 20), c.max_file_size.unwrap_or(256 << 10)));
+
+        let in_flight = config.max_requests_in_flight.filter(|&n| n > 0).map(Semaphore::new);
+        let per_ip = config.max_connections_per_ip.filter(|&n| n > 0).map(ConnectionLimiter::new);
+
+        #[cfg(feature = "record")]
+        let recorder = config.record.as_deref().map(record::Recorder::open).transpose()?;
+
+        let compressed = config.compression.as_ref()
+            .and_then(|c| c.cache_size)
+            .map(|size| FileCache::new(size, size));
+
+        Ok(Self {
+            config,
+            logger,
+            requests: AtomicU64::new(0),
+            active: AtomicUsize::new(0),
+            bytes_sent: AtomicU64::new(0),
+            hashes: ContentHashes::default(),
+            cache,
+            compressed,
+            in_flight,
+            draining: AtomicBool::new(false),
+            accept_limiter,
+            per_ip,
+            #[cfg(feature = "record")]
+            recorder,
+        })
+    }
+
+    fn keepalive(&self, stream: &net::TcpStream) -> io::Result<()> {
+        match self.config.tcp_keepalive {
+            Some(secs) => SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(Duration::from_secs(secs))),
+            None => Ok(()),
+        }
+    }
+
+    async fn accept(&self, socket: &net::TcpListener) -> io::Result<(net::TcpStream, SocketAddr, Option<ConnectionGuard>)> {
+        if let Some(limiter) = &self.accept_limiter {
+            limiter.acquire().await;
+        }
+
+        let backoff = Duration::from_millis(self.config.accept_backoff.unwrap_or(100));
+
+        loop {
+            match socket.accept().await {
+                Ok((stream, remote)) => {
+                    if let Err(e) = self.keepalive(&stream) {
+                        eprintln!("failed to set TCP keepalive for {remote}: {e}");
+                    }
+
+                    match &self.per_ip {
+                        Some(limiter) => match limiter.try_acquire(remote.ip()) {
+                            Some(guard) => return Ok((stream, remote, Some(guard))),
+                            None => drop(stream),
+                        },
+                        None => return Ok((stream, remote, None)),
+                    }
+                },
+                Err(e) if transient_accept_error(&e) => {
+                    eprintln!("failed to accept connection, retrying: {e}");
+                    tokio::time::sleep(backoff).await;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+enum Resource {
+    File(File, Box<fs::Metadata>),
+    Directory,
+    NotFound,
+    Forbidden,
+}
+
+enum Resolved {
+    File(File, Box<fs::Metadata>, String),
+    Listing,
+    NotFound,
+    Forbidden,
+}
+
+enum Selection {
+    Range(ByteRange),
+    Gzip,
+    Identity,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scheme {
+    Http,
+    Https,
+}
+
+#[derive(Clone)]
 struct ServerInfo {
+    server: Arc<Server>,
     root: String,
     host: String,
+    scheme: Scheme,
     port: u16,
+    remote: Option<SocketAddr>,
+    tls: Option<TlsInfo>,
 }
 
 impl ServerInfo {
-    fn new(root: String, host: String, port: u16) -> Self {
-        Self { root, host, port }
+    fn new(server: Arc<Server>, scheme: Scheme, port: u16, remote: Option<SocketAddr>, tls: Option<TlsInfo>) -> Self {
+        let (root, host) = match scheme {
+            Scheme::Http => server.config.http.as_ref().map(|h| (h.root.clone(), h.host.clone())),
+            Scheme::Https => server.config.https.as_ref().map(|h| (h.root.clone(), h.host.clone())),
+        }.unwrap_or_default();
+
+        let root = root.unwrap_or(server.config.root.clone());
+        let host = host.unwrap_or(server.config.host.clone());
+
+        Self { server, root, host, scheme, port, remote, tls }
+    }
+
+    fn with_root(&self, root: &str) -> Self {
+        Self { root: root.into(), ..self.clone() }
     }
 
     fn path(&self, pathstr: &str) -> String {
         format!("{}/{}", self.root, pathstr)
     }
 
+    async fn resolve(&self, resource: &str) -> io::Result<Resource> {
+        if self.server.config.hide_dotfiles.unwrap_or(false) && resource.split('/').any(|s| s.starts_with('.')) {
+            return Ok(Resource::NotFound);
+        }
+
+        let file = match File::open(self.path(resource)).await {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Resource::NotFound),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return Ok(Resource::Forbidden),
+            Err(e) => return Err(e),
+        };
+
+        let md = file.metadata().await?;
+
+        if md.is_dir() {
+            Ok(Resource::Directory)
+        } else {
+            Ok(Resource::File(file, Box::new(md)))
+        }
+    }
+
+    async fn lookup(&self, resource: &str) -> io::Result<Resolved> {
+        let target = self.resolve(resource).await?;
+        let is_dir = matches!(target, Resource::Directory);
+
+        let mut file = match target {
+            Resource::File(file, md) => Some((file, md)),
+            Resource::Forbidden => return Ok(Resolved::Forbidden),
+            _ => None,
+        };
+
+        for strategy in self.server.config.strategies() {
+            match strategy {
+                Strategy::File => if let Some((file, md)) = file.take() {
+                    return Ok(Resolved::File(file, md, resource.into()));
+                },
+                Strategy::Index if is_dir => for index in self.server.config.index_files() {
+                    let index = format!("{}/{}", resource.trim_end_matches('/'), index);
+
+                    match self.resolve(&index).await? {
+                        Resource::File(file, md) => return Ok(Resolved::File(file, md, index)),
+                        Resource::Forbidden => return Ok(Resolved::Forbidden),
+                        _ => (),
+                    }
+                },
+                Strategy::Spa => if let Some(fallback) = &self.server.config.spa_fallback {
+                    if let Resource::File(file, md) = self.resolve(fallback).await? {
+                        return Ok(Resolved::File(file, md, fallback.clone()));
+                    }
+                },
+                Strategy::Autoindex if is_dir => return Ok(Resolved::Listing),
+                _ => (),
+            }
+        }
+
+        Ok(Resolved::NotFound)
+    }
+
+    async fn negotiate(&self, path: &str, accept_language: &str) -> io::Result<Option<(File, Box<fs::Metadata>, String)>> {
+        for language in negotiate::languages(accept_language) {
+            let variant = negotiate::variant(path, &language);
+
+            if let Resource::File(file, md) = self.resolve(&variant).await? {
+                return Ok(Some((file, md, variant)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn etag(&self, resource: &str, md: &fs::Metadata) -> io::Result<String> {
+        match self.server.config.etag.unwrap_or_default() {
+            EtagMode::Metadata => Ok(etag::metadata(md)),
+            EtagMode::Content => self.server.hashes.get(Path::new(&self.path(resource)), md).await,
+        }
+    }
+
+    async fn cached(&self, resource: &str, file: &mut File, md: &fs::Metadata, revalidate: bool) -> io::Result<Option<(Arc<Vec<u8>>, Option<Duration>)>> {
+        let Some(cache) = self.server.cache.as_ref().filter(|c| c.accepts(md)) else {
+            return Ok(None);
+        };
+
+        let path = PathBuf::from(self.path(resource));
+
+        if let Some((data, age)) = cache.get(&path, md).filter(|_| !revalidate) {
+            return Ok(Some((data, Some(age))));
+        }
+
+        let mut data = vec![];
+        file.read_to_end(&mut data).await?;
+
+        let data = Arc::new(data);
+        cache.insert(&path, md, data.clone());
+
+        Ok(Some((data, None)))
+    }
+
+    fn admit(&self, request: &Request, path: &str, expect: Option<&str>) -> Option<u32> {
+        let length = request.content_length().unwrap_or(0);
+
+        if let Some(expect) = expect {
+            if !expect.trim().eq_ignore_ascii_case("100-continue") {
+                return Some(417);
+            }
+
+            if !self.allowed_methods(path).contains(&request.method) {
+                return Some(405);
+            }
+        }
+
+        self.server.config.max_body_size.filter(|&max| length > max).map(|_| 413)
+    }
+
+    fn writable(&self, resource: &str) -> bool {
+        self.server.config.writable.iter().flatten().any(|prefix| resource.starts_with(prefix.as_str()))
+    }
+
+    fn allowed_methods(&self, resource: &str) -> Vec<Method> {
+        let mut methods = vec![Method::Get, Method::Head, Method::Options];
+
+        if self.writable(resource) {
+            methods.extend([Method::Put, Method::Delete]);
+        }
+
+        if let Some(allowed) = self.server.config.methods(resource) {
+            let get = allowed.contains(&Method::Get);
+            methods.retain(|m| allowed.contains(m) || (*m == Method::Head && get));
+        }
+
+        methods
+    }
+
+    fn allow(&self, resource: &str) -> String {
+        self.allowed_methods(resource).iter().map(|m| m.to_string()).collect::<Vec<_>>().join(", ")
+    }
+
+    fn https_url(&self, resource: &str) -> Option<String> {
+        let https = self.server.config.https.as_ref()?;
+        let host = https.host.as_ref().unwrap_or(&self.server.config.host);
+
+        Some(match https.port.unwrap_or(443) {
+            443 => format!("https://{}{}", host, resource),
+            port => format!("https://{}:{}{}", host, port, resource),
+        })
+    }
+
+    fn misdirected(&self, request: &Request) -> bool {
+        let sni = self.tls.as_ref().and_then(|tls| tls.server_name.as_deref());
+        let host = request.header::<String>("Host");
+
+        match (sni, host) {
+            (Some(sni), Some(host)) => {
+                let name = host.rsplit_once(':').filter(|(_, p)| p.parse::<u16>().is_ok()).map_or(host.as_str(), |(h, _)| h);
+                !name.eq_ignore_ascii_case(sni)
+            },
+            _ => false,
+        }
+    }
+
+    fn host_matches(&self, host: &str, expected: &str) -> bool {
+        let default_port = match self.scheme {
+            Scheme::Http => ":80",
+            Scheme::Https => ":443",
+        };
+
+        let host = host.strip_suffix(default_port).unwrap_or(host);
+
+        host.eq_ignore_ascii_case(expected) || host.eq_ignore_ascii_case(&format!("{}:{}", expected, self.port))
+    }
+
     fn host_check(&self, host: &str) -> bool {
-        *host == self.host || *host == format!("{}:{}", self.host, self.port)
+        self.host_matches(host, &self.host)
     }
+
+    fn canonical_url(&self, host: &str, resource: &str) -> Option<String> {
+        self.server.config.redirect_hosts.iter().flatten().find(|alias| self.host_matches(host, alias))?;
+
+        let (scheme, default_port) = match self.scheme {
+            Scheme::Http => ("http", 80),
+            Scheme::Https => ("https", 443),
+        };
+
+        Some(match self.port {
+            port if port == default_port => format!("{scheme}://{}{resource}", self.host),
+            port => format!("{scheme}://{}:{port}{resource}", self.host),
+        })
+    }
+}
+
+fn transient_accept_error(e: &io::Error) -> bool {
+    matches!(e.kind(),
+        io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::Interrupted
+        | io::ErrorKind::WouldBlock
+        | io::ErrorKind::TimedOut
+        | io::ErrorKind::OutOfMemory)
+        || e.raw_os_error().is_some_and(resource_exhausted)
 }
 
-fn load_certs(path: &Path) -> io::Result<Vec<pki_types::CertificateDer<'static>>> {
-    rustls_pemfile::certs(&mut std::io::BufReader::new(fs::File::open(path)?)).collect()
+#[cfg(unix)]
+fn resource_exhausted(code: i32) -> bool {
+    [libc::EMFILE, libc::ENFILE, libc::ENOBUFS, libc::ENOMEM].contains(&code)
 }
 
-fn load_key(path: &Path) -> io::Result<Option<pki_types::PrivateKeyDer<'static>>> {
-    rustls_pemfile::private_key(&mut io::BufReader::new(fs::File::open(path)?))
+#[cfg(not(unix))]
+fn resource_exhausted(_code: i32) -> bool {
+    false
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config: Config = toml::from_str(&fs::read_to_string("minhttp.toml")?)?;
-    let httphandle: Option<tokio::task::JoinHandle<Result<()>>>;
-    let httpshandle: Option<tokio::task::JoinHandle<Result<()>>>;
+    #[cfg(feature = "record")]
+    if let [_, command, file, address] = &std::env::args().collect::<Vec<_>>()[..] {
+        if command == "replay" {
+            let failures = record::replay_file(Path::new(file), address).await?;
+            println!("{failures} exchange(s) differed");
+
+            return Ok(());
+        }
+    }
+
+    let config = Config::load(Path::new("minhttp.toml"))?;
+    let server = Arc::new(Server::new(config.clone())?);
+
+    if let Some(debug) = &config.debug {
+        eprintln!("warning: debug echo endpoint enabled at {}", debug.echo_path);
+    }
 
-    httphandle = config.http.clone().map(|http| {
-        let config = config.clone();
+    let httphandle: Option<tokio::task::JoinHandle<Result<()>>> = config.http.clone().map(|http| {
+        let server = server.clone();
 
         let address = http.address.unwrap_or("127.0.0.1".into());
         let port = http.port.unwrap_or(80);
@@ -85,13 +464,14 @@ async fn main() -> Result<()> {
             let socket = net::TcpListener::bind(format!("{}:{}", address, port)).await?;
 
             loop {
-                let (connection, _) = socket.accept().await?;
-                
-                let root = config.root.clone();
-                let host = config.host.clone();
+                let (connection, remote, guard) = server.accept(&socket).await?;
+
+                let server = server.clone();
 
                 tokio::spawn(async move {
-                    match handle_connection(connection, ServerInfo::new(root, host, port)).await {
+                    let _guard = guard;
+
+                    match handle_connection(connection, ServerInfo::new(server, Scheme::Http, port, Some(remote), None)).await {
                         Ok(()) => (),
                         Err(e) => {
                             eprintln!("an error occured while handling request: {e}");
@@ -102,36 +482,36 @@ async fn main() -> Result<()> {
         })
     });
 
-    httpshandle = config.https.clone().map(|https| {
-        let config = config.clone();
+    let httpshandle: Option<tokio::task::JoinHandle<Result<()>>> = config.https.clone().map(|https| {
+        let server = server.clone();
 
         let address = https.address.unwrap_or("127.0.0.1".into());
         let port = https.port.unwrap_or(443);
 
-        let certs = load_certs(&https.cert).expect("failed to get certificate");
+        let default = tls::load_certified_key(&https.cert, &https.key).expect("failed to load certificate and key");
+        let mut resolver = tls::CertResolver::new(default);
 
-        let key = load_key(&https.key)
-            .expect("failed to open or read key file")
-            .expect("no key found in file");
+        if let Some(dir) = &https.cert_dir {
+            resolver.scan(dir).expect("failed to read certificate directory");
+        }
 
         let rustlsconfig = tokio_rustls::rustls::ServerConfig::builder()
             .with_no_client_auth()
-            .with_single_cert(certs, key)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
-            .expect("failed to create TLS config");
+            .with_cert_resolver(Arc::new(resolver));
 
         tokio::spawn(async move {
             let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(rustlsconfig));
             let socket = net::TcpListener::bind(format!("{}:{}", address, port)).await?;
 
             loop {
-                let (stream, _) = socket.accept().await?;
+                let (stream, remote, guard) = server.accept(&socket).await?;
                 let acceptor = acceptor.clone();
 
-                let root = config.root.clone();
-                let host = config.host.clone();
+                let server = server.clone();
 
                 tokio::spawn(async move {
+                    let _guard = guard;
+
                     let stream = match acceptor.accept(stream).await {
                         Ok(s) => s,
                         Err(e) => {
@@ -140,7 +520,9 @@ async fn main() -> Result<()> {
                         }
                     };
 
-                    match handle_connection(stream, ServerInfo::new(root, host, port)).await {
+                    let tls = TlsInfo::new(stream.get_ref().1);
+
+                    match handle_connection(stream, ServerInfo::new(server, Scheme::Https, port, Some(remote), Some(tls))).await {
                         Ok(()) => (),
                         Err(e) => {
                             eprintln!("an error occured while handling request: {e}");
@@ -151,69 +533,792 @@ async fn main() -> Result<()> {
         })
     });
 
-    let _ = tokio::join!(
-        httphandle.unwrap_or(tokio::spawn(async { Ok(()) })),
-        httpshandle.unwrap_or(tokio::spawn(async { Ok(()) })),
-    );
+    let listeners = async {
+        tokio::join!(
+            httphandle.unwrap_or(tokio::spawn(async { Ok(()) })),
+            httpshandle.unwrap_or(tokio::spawn(async { Ok(()) })),
+        )
+    };
+
+    tokio::select! {
+        _ = listeners => (),
+        signal = shutdown_signal() => {
+            signal?;
+
+            let grace = config.shutdown_grace.unwrap_or(10);
+            eprintln!("shutting down, draining connections for {grace}s");
+
+            server.draining.store(true, Ordering::Relaxed);
+
+            let deadline = Instant::now() + Duration::from_secs(grace);
+            let mut ticks = 0;
+
+            loop {
+                let active = server.active.load(Ordering::Relaxed);
+
+                if active == 0 || Instant::now() >= deadline {
+                    let served = server.requests.load(Ordering::Relaxed);
+                    let sent = server.bytes_sent.load(Ordering::Relaxed);
+                    eprintln!("exiting with {active} request(s) in flight, {served} request(s) served, {sent} byte(s) sent");
+                    break;
+                }
+
+                if ticks % 10 == 0 {
+                    eprintln!("waiting on {active} request(s) in flight");
+                }
+
+                ticks += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        },
+    }
 
     Ok(())
 }
 
-async fn handle_connection<S: AsyncRead + AsyncWrite>(stream: S, config: ServerInfo) -> Result<()> {
-    let http = HttpStream::new(stream);
+#[cfg(unix)]
+async fn shutdown_signal() -> io::Result<()> {
+    let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+
+    tokio::select! {
+        result = signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> io::Result<()> {
+    signal::ctrl_c().await
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(stream: S, config: ServerInfo) -> Result<()> {
+    let idle_timeout = Duration::from_secs(config.server.config.idle_timeout.unwrap_or(60));
+    let write_timeout = config.server.config.write_timeout.map(Duration::from_secs).unwrap_or(idle_timeout);
+
+    let stream = IdleTimeout::new(stream, Some(idle_timeout).filter(|t| !t.is_zero()))
+        .with_write_timeout(Some(write_timeout).filter(|t| !t.is_zero()));
+
+    let http = HttpStream::with_limits(stream, config.server.config.limits());
     let (mut reader, mut writer) = http.split();
 
+    let header_timeout = config.server.config.header_timeout.map(Duration::from_secs).filter(|t| !t.is_zero());
+
     loop {
-        let msg = match reader.read_obj().await {
-            Ok(m) => m,
-            Err(e) =>
-                return e.downcast::<MessageParseError>().and_then(|msg_err| {
-                    match msg_err {
-                        MessageParseError::ConnectionClosed => Ok(()),
-                        _ => Err(msg_err.into()),
-                    }
-                }),
+        match reader.ready().await {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let received = Instant::now();
+        let head = reader.next_request_streaming();
+
+        let head = match header_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, head).await
+                .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "header timeout").into())),
+            None => head.await,
         };
 
-        match msg {
-            Message::Request(req) => {
-                println!("{} {}", req.method, req.resource);
-                writer.write_obj(&create_response(req, &config).await?).await?;
+        let (req, mut body) = match head {
+            Ok(Some(r)) => r,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                let code = match e.downcast_ref::<MessageParseError>() {
+                    _ if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::TimedOut) => 408,
+                    Some(MessageParseError::UriTooLong) => 414,
+                    Some(MessageParseError::HeadersTooLarge) => 431,
+                    Some(MessageParseError::Http2Preface | MessageParseError::UnsupportedVersion) => 505,
+                    Some(MessageParseError::UnsupportedTransferEncoding) => 501,
+                    Some(MessageParseError::ConnectionClosed) => return Err(e),
+                    Some(_) => 400,
+                    None if e.is::<TargetParseError>() || e.is::<VersionParseError>() || e.is::<FromUtf8Error>() || e.is::<ParseIntError>() => 400,
+                    None => return Err(e),
+                };
+
+                let mut response = error(code, &config).await?;
+                response.headers.insert("Connection".into(), "close".into());
+
+                let before = writer.written();
+                let result = writer.write_obj(&response).await;
+                let sent = writer.written() - before;
+                config.server.bytes_sent.fetch_add(sent, Ordering::Relaxed);
+
+                config.server.logger.log(&LogRecord {
+                    remote: config.remote,
+                    time: SystemTime::now(),
+                    method: "-".into(),
+                    resource: "-".into(),
+                    version: "-".into(),
+                    status: code,
+                    bytes: sent as usize,
+                    referer: None,
+                    user_agent: None,
+                    duration: received.elapsed(),
+                });
+
+                return result;
             },
+        };
 
-            Message::Response(_) => {
-                writer.write_obj(&error(400, &config).await?).await?;
+        let start = Instant::now();
+        let method = req.method;
+        let version = req.version;
+        let keep_alive = req.keep_alive();
+        config.server.requests.fetch_add(1, Ordering::Relaxed);
+        let _active = Active::enter(&config.server.active);
+
+        let mut record = LogRecord {
+            remote: config.remote,
+            time: SystemTime::now(),
+            method: req.method.to_string(),
+            resource: req.resource.clone(),
+            version: req.version.to_string(),
+            status: 0,
+            bytes: 0,
+            referer: req.header("Referer"),
+            user_agent: req.header("User-Agent"),
+            duration: Default::default(),
+        };
+
+        if let Some(connect) = config.server.config.connect.as_ref().filter(|_| method == Method::Connect) {
+            let upstream = match req.target.authority.as_deref().filter(|_| req.target.form == Form::Authority) {
+                Some(authority) if connect.allows(authority) => net::TcpStream::connect(authority).await.map_err(|e| {
+                    eprintln!("failed to connect to {authority}: {e}");
+                    502
+                }),
+                Some(_) => Err(403),
+                None => Err(400),
+            };
+
+            let mut response = match upstream {
+                Ok(_) => {
+                    let mut response = Response::from_bytes(Version::Http11, 200, vec![]);
+                    response.headers.remove("Content-Length");
+                    response
+                },
+                Err(code) => error(code, &config).await?,
+            };
+
+            writer.write_response(&mut response).await?;
+
+            record.status = response.code;
+            record.duration = start.elapsed();
+            config.server.logger.log(&record);
+
+            return match upstream {
+                Ok(upstream) => tunnel(reader.into_inner(), writer.into_inner(), upstream).await,
+                Err(_) => Ok(()),
+            };
+        }
+
+        let path = req.path();
+
+        #[cfg(feature = "record")]
+        let head = config.server.recorder.as_ref().map(|_| req.head()).transpose()?;
+
+        #[cfg(feature = "record")]
+        if head.is_some() {
+            body.capture(record::MAX_BODY);
+        }
+
+        let permit = config.server.in_flight.as_ref().map(|s| s.try_acquire());
+        let expect = req.header::<String>("Expect");
+        let rejected = config.admit(&req, &path, expect.as_deref());
+
+        if expect.is_some() && version != Version::Http10 && rejected.is_none() && !matches!(permit, Some(Err(_))) {
+            writer.write_obj(&Response::from_bytes(Version::Http11, 100, vec![])).await?;
+        }
+
+        let result = match config.server.config.handler_timeout {
+            _ if matches!(permit, Some(Err(_))) => overloaded(&config).await,
+            _ if rejected.is_some() => rejected_response(rejected, &path, &config).await,
+            Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), create_response(req, &mut body, &config)).await {
+                Ok(result) => result,
+                Err(_) => error(504, &config).await,
             },
+            None => create_response(req, &mut body, &config).await,
+        };
+
+        let mut response = match result {
+            Ok(response) => response,
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::TimedOut) => error(408, &config).await?,
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::FileTooLarge) => error(413, &config).await?,
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::InvalidData) => error(400, &config).await?,
+            Err(e) => {
+                eprintln!("an error occured while handling request: {e}");
+                error(500, &config).await?
+            },
+        };
+
+        for (name, value) in config.server.config.headers(&path) {
+            response.headers.retain(|k, _| !k.eq_ignore_ascii_case(&name));
+            response.headers.insert(name, value);
+        }
+
+        response.version = version;
+
+        if version == Version::Http10 && response.chunked() {
+            response.headers.retain(|k, _| !k.eq_ignore_ascii_case("Transfer-Encoding"));
+        }
+
+        if let Some(link) = config.server.config.preload(&path).filter(|_| (200..300).contains(&response.code)) {
+            response.headers.insert("Link".into(), link);
+        }
+
+        if method == Method::Head {
+            response.discard_body();
+        }
+
+        let last = !keep_alive
+            || body.remaining().is_none_or(|r| r > config.server.config.max_drain.unwrap_or(65536))
+            || !response.framed()
+            || (rejected.is_some() && expect.is_some())
+            || response.code == 408
+            || config.server.draining.load(Ordering::Relaxed);
+
+        response.headers.insert("Connection".into(), if last { "close" } else { "keep-alive" }.into());
+
+        #[cfg(feature = "record")]
+        if let (Some(recorder), Some(head)) = (&config.server.recorder, &head) {
+            recorder.record(head, body.captured(), &response)?;
+        }
+
+        let before = writer.written();
+        writer.write_response(&mut response).await?;
+
+        if !last {
+            body.drain().await?;
+        }
+
+        drop(permit);
+
+        let sent = writer.written() - before;
+        config.server.bytes_sent.fetch_add(sent, Ordering::Relaxed);
+
+        record.status = response.code;
+        record.bytes = sent as usize;
+        record.duration = start.elapsed();
+
+        config.server.logger.log(&record);
+
+        if last {
+            return Ok(());
         }
     }
 }
 
+async fn tunnel<R, W>(mut client_reader: R, mut client_writer: W, upstream: net::TcpStream) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (mut upstream_reader, mut upstream_writer) = upstream.into_split();
+
+    let outbound = async {
+        tokio::io::copy(&mut client_reader, &mut upstream_writer).await?;
+        upstream_writer.shutdown().await
+    };
+
+    let inbound = async {
+        tokio::io::copy(&mut upstream_reader, &mut client_writer).await?;
+        client_writer.shutdown().await
+    };
+
+    tokio::try_join!(outbound, inbound)?;
+
+    Ok(())
+}
+
+const ACME_CHALLENGE_PREFIX: &str = "/.well-known/acme-challenge/";
+
+const DEFAULT_FAVICON: &[u8] = &[
+    0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+    0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x30, 0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00,
+    0x28, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+];
+
+fn default_resource(path: &str, config: &ServerInfo) -> Option<Response> {
+    let (body, content_type) = match path {
+        "/robots.txt" => (config.server.config.robots_txt.clone()?.into_bytes(), "text/plain; charset=utf-8"),
+        "/favicon.ico" if config.server.config.default_favicon.unwrap_or(false) => (DEFAULT_FAVICON.to_vec(), "image/x-icon"),
+        _ => return None,
+    };
+
+    let mut response = Response::from_bytes(Version::Http11, 200, body);
+    response.headers.insert("Content-Type".into(), content_type.into());
+
+    Some(response)
+}
+
+fn merge_slashes(resource: &str) -> String {
+    let (path, query) = match resource.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (resource, None),
+    };
+
+    let mut merged = path.chars().fold(String::new(), |mut out, c| {
+        if !(c == '/' && out.ends_with('/')) {
+            out.push(c);
+        }
+
+        out
+    });
+
+    if let Some(query) = query {
+        merged.push('?');
+        merged.push_str(query);
+    }
+
+    merged
+}
+
+async fn rejected_response(code: Option<u32>, path: &str, config: &ServerInfo) -> Result<Response> {
+    let code = code.unwrap_or(400);
+    let mut response = error(code, config).await?;
+
+    if code == 405 {
+        response.headers.insert("Allow".into(), config.allow(path));
+    }
+
+    Ok(response)
+}
+
+async fn overloaded(config: &ServerInfo) -> Result<Response> {
+    let mut response = error(503, config).await?;
+    response.headers.insert("Retry-After".into(), "1".into());
+
+    Ok(response)
+}
+
+async fn maintenance(page: &Path, config: &ServerInfo) -> Result<Response> {
+    let mut response = match File::open(page).await {
+        Ok(mut file) => Response::serve_file_with_code(Version::Http11, 503, &mut file, page).await?,
+        Err(e) => {
+            eprintln!("failed to open maintenance page {}: {e}", page.display());
+            error(503, config).await?
+        },
+    };
+
+    response.headers.insert("Cache-Control".into(), "no-store".into());
+    response.headers.insert("Retry-After".into(), config.server.config.shutdown_grace.unwrap_or(10).to_string());
+
+    Ok(response)
+}
+
 fn get_filepath_from_code(code: u32) -> String {
     format!(".errors/{code}.html")
 }
 
 async fn error(code: u32, config: &ServerInfo) -> Result<Response> {
-    let mut file = File::open(config.path(&get_filepath_from_code(code))).await?;
-    Response::serve_file_with_code(Version::Http11, code, &mut file).await
+    let path = PathBuf::from(config.path(&get_filepath_from_code(code)));
+
+    let mut file = match File::open(&path).await {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let body = format!("{code} {}\n", Response::message(code).unwrap_or("Unknown"));
+            return Ok(Response::from_bytes(Version::Http11, code, body.into_bytes()));
+        },
+        Err(e) => return Err(e.into()),
+    };
+
+    Response::serve_file_with_code(Version::Http11, code, &mut file, &path).await
+}
+
+fn echo(request: &Request, body: &[u8], tls: Option<&TlsInfo>) -> Result<Response> {
+    let path = &request.target.path;
+
+    let query: Vec<(&str, &str)> = request.target.query.as_deref().unwrap_or("").split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once('=').unwrap_or((p, "")))
+        .collect();
+
+    let body = serde_json::to_vec_pretty(&serde_json::json!({
+        "method": request.method.to_string(),
+        "path": path,
+        "query": query,
+        "version": request.version.to_string(),
+        "headers": request.headers,
+        "body": String::from_utf8_lossy(body),
+        "tls": tls,
+    }))?;
+
+    let mut response = Response::from_bytes(Version::Http11, 200, body);
+    response.headers.insert("Content-Type".into(), "application/json".into());
+    response.headers.insert("Cache-Control".into(), "no-store".into());
+
+    Ok(response)
 }
 
-async fn create_response(request: Request, config: &ServerInfo) -> Result<Response> {
-    if request.headers.get("Host").filter(|h| config.host_check(*h)).is_none() {
+async fn write_resource<R>(request: &Request, body: &mut RequestBody<'_, R>, resource: &str, config: &ServerInfo) -> Result<Response>
+where
+    R: AsyncBufRead + Unpin
+{
+    let resource = resource.split('?').next().unwrap_or("");
+
+    if resource.ends_with('/') || resource.split('/').any(|s| s == "..") {
+        return error(403, config).await;
+    }
+
+    let path = PathBuf::from(config.path(resource));
+    let md = tokio::fs::metadata(&path).await.ok().filter(|md| md.is_file());
+
+    let etag = match &md {
+        Some(md) => Some(config.etag(resource, md).await?),
+        None => None,
+    };
+
+    let modified = md.as_ref().and_then(|md| md.modified().ok());
+
+    if conditional::evaluate(request, etag.as_deref()) != Precondition::Proceed
+        || conditional::unmodified_since(request, modified) != Precondition::Proceed {
+        return error(412, config).await;
+    }
+
+    let code = match request.method {
+        Method::Put => writable::put(&path, request, body, config.server.config.upload_modes()).await?,
+        _ => writable::delete(&path).await?,
+    };
+
+    match code {
+        201 | 204 => Ok(Response::from_bytes(Version::Http11, code, vec![])),
+        _ => error(code, config).await,
+    }
+}
+
+async fn create_response<R>(request: Request, body: &mut RequestBody<'_, R>, config: &ServerInfo) -> Result<Response>
+where
+    R: AsyncBufRead + Unpin
+{
+    if config.server.config.check_sni.unwrap_or(false) && config.misdirected(&request) {
+        return error(421, config).await;
+    }
+
+    if matches!(request.method, Method::Connect | Method::Trace) {
+        let code = config.server.config.disabled_method_status.unwrap_or(405);
+        let mut response = error(code, config).await?;
+
+        if code == 405 {
+            response.headers.insert("Allow".into(), config.allow(&request.path()));
+        }
+
+        return Ok(response);
+    }
+
+    match request.target.form {
+        Form::Asterisk if request.method == Method::Options => {
+            let mut response = Response::from_bytes(Version::Http11, 204, vec![]);
+            response.headers.insert("Allow".into(), "GET, HEAD, OPTIONS".into());
+
+            return Ok(response);
+        },
+        Form::Asterisk | Form::Authority => return error(400, config).await,
+        Form::Absolute if !matches!(request.target.scheme.as_deref(), Some("http" | "https")) => return error(400, config).await,
+        _ => (),
+    }
+
+    if config.server.config.health_path.as_ref().is_some_and(|p| *p == request.target.path) {
+        let (code, status) = match config.server.draining.load(Ordering::Relaxed) {
+            true => (503, "draining"),
+            false => (200, "ok"),
+        };
+
+        let served = config.server.requests.load(Ordering::Relaxed);
+        let body = format!("{status}\nrequests: {served}\n");
+
+        let mut response = Response::from_bytes(Version::Http11, code, body.into());
+        response.headers.insert("Content-Type".into(), "text/plain; charset=utf-8".into());
+        response.headers.insert("Cache-Control".into(), "no-store".into());
+
+        return Ok(response);
+    }
+
+    if let Some(page) = config.server.config.maintenance_page.as_ref().filter(|_| config.server.draining.load(Ordering::Relaxed)) {
+        return maintenance(page, config).await;
+    }
+
+    let host = request.target.authority.clone()
+        .or_else(|| request.header("Host"))
+        .or_else(|| (request.version == Version::Http10).then(|| config.host.clone()));
+
+    let origin = match &request.target.query {
+        Some(query) => format!("{}?{query}", request.target.path),
+        None => request.target.path.clone(),
+    };
+
+    if let Some(location) = host.as_deref().and_then(|h| config.canonical_url(h, &origin)) {
+        return Ok(Response::redirect(Version::Http11, 301, &location, &[]));
+    }
+
+    let catch_all;
+
+    let config = match host.filter(|h| config.host_check(h)) {
+        Some(_) => config,
+        None => match &config.server.config.catch_all {
+            Some(CatchAllConfig { redirect: Some(url), retry_after, .. }) => {
+                let location = format!("{}{}", url.trim_end_matches('/'), origin);
+                let retry_after = retry_after.map(|secs| secs.to_string());
+                let headers: Vec<(&str, &str)> = retry_after.iter().map(|v| ("Retry-After", v.as_str())).collect();
+
+                return Ok(Response::redirect(Version::Http11, 302, &location, &headers));
+            },
+            Some(CatchAllConfig { root: Some(root), .. }) => {
+                catch_all = config.with_root(root);
+                &catch_all
+            },
+            _ => return error(400, config).await,
+        },
+    };
+
+    let mut resource = origin.clone();
+
+    if resource.split('?').next().is_some_and(|p| p.contains("//")) {
+        match config.server.config.slashes.unwrap_or_default() {
+            SlashPolicy::Merge => resource = merge_slashes(&resource),
+            SlashPolicy::Reject => return error(400, config).await,
+        }
+    }
+
+    if matches!(request.target.form, Form::Origin | Form::Absolute) && uri::climbs(&request.target.path) {
+        return error(403, config).await;
+    }
+
+    let target = request.path();
+
+    if target.contains('\0') {
         return error(400, config).await;
     }
 
-    let md = match fs::metadata(config.path(&request.resource)) {
-        Ok(m) => m,
-        Err(_) => return error(404, config).await,
+    if target.len() > config.server.config.max_path_length.unwrap_or(4096) {
+        return error(414, config).await;
+    }
+
+    if target.split('/').filter(|s| !s.is_empty()).count() > config.server.config.max_path_depth.unwrap_or(32) {
+        return error(404, config).await;
+    }
+
+    if config.server.config.portable_paths.unwrap_or(false) && !target.split('/').filter(|s| !s.is_empty()).all(uri::portable_segment) {
+        return error(404, config).await;
+    }
+
+    if let Some(debug) = &config.server.config.debug {
+        let allowed = debug.allow_remote.unwrap_or(false) || config.remote.is_some_and(|r| r.ip().is_loopback());
+
+        if target == debug.echo_path && allowed {
+            return echo(&request, &body.bytes().await?, config.tls.as_ref());
+        }
+    }
+
+    if !config.allowed_methods(&target).contains(&request.method) {
+        let mut response = error(405, config).await?;
+        response.headers.insert("Allow".into(), config.allow(&target));
+
+        return Ok(response);
+    }
+
+    match request.method {
+        Method::Put | Method::Delete => return write_resource(&request, body, &target, config).await,
+        Method::Options => {
+            let mut response = Response::from_bytes(Version::Http11, 204, vec![]);
+            response.headers.insert("Allow".into(), config.allow(&target));
+
+            return Ok(response);
+        },
+        _ => (),
+    }
+
+    if let Some(token) = target.strip_prefix(ACME_CHALLENGE_PREFIX) {
+        if let Some(dir) = config.server.config.acme_challenge.as_ref().filter(|_| config.scheme == Scheme::Http) {
+            if token.is_empty() || !token.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') {
+                return error(404, config).await;
+            }
+
+            let path = dir.join(token);
+
+            return match File::open(&path).await {
+                Ok(mut file) => Response::serve_file(Version::Http11, &mut file, &path).await,
+                Err(_) => error(404, config).await,
+            };
+        }
+    }
+
+    let upgrade = config.scheme == Scheme::Http
+        && config.server.config.upgrade_insecure_requests.unwrap_or(false)
+        && request.header::<String>("Upgrade-Insecure-Requests").is_some_and(|v| v.trim() == "1");
+
+    if let Some(location) = config.https_url(&resource).filter(|_| upgrade) {
+        return Ok(Response::redirect(Version::Http11, 307, &location, &[("Vary", "Upgrade-Insecure-Requests")]));
+    }
+
+    let (mut file, mut file_md, path) = match config.lookup(&target).await? {
+        Resolved::File(file, md, path) => (file, md, path),
+        Resolved::Listing => {
+            let dir = config.path(&target);
+            let accept = request.header::<String>("Accept").unwrap_or_default();
+            let hide_dotfiles = config.server.config.hide_dotfiles.unwrap_or(false);
+
+            let (body, content_type) = match negotiate::media_type(&accept, &["text/html", "application/json"]) {
+                Some("application/json") => (autoindex::render_json(Path::new(&dir), hide_dotfiles).await?, "application/json"),
+                _ => (autoindex::render(Path::new(&dir), &target, hide_dotfiles).await?, "text/html; charset=utf-8"),
+            };
+
+            let mut response = Response::from_bytes(Version::Http11, 200, body);
+            response.headers.insert("Content-Type".into(), content_type.into());
+            response.headers.insert("Vary".into(), "Accept".into());
+
+            return Ok(response);
+        },
+        Resolved::NotFound if conditional::evaluate(&request, None) == Precondition::Failed => return error(412, config).await,
+        Resolved::NotFound => return match default_resource(&target, config) {
+            Some(response) => Ok(response),
+            None => error(404, config).await,
+        },
+        Resolved::Forbidden => return error(403, config).await,
     };
 
-    let path = if md.is_dir() {
-        format!("{}/index.html", &request.resource)
-    } else {
-        request.resource
+    let negotiate = config.server.config.negotiate_language.unwrap_or(false);
+    let mut content_location = None;
+
+    if let Some(accept_language) = request.header::<String>("Accept-Language").filter(|_| negotiate) {
+        if let Some((variant, md, location)) = config.negotiate(&path, &accept_language).await? {
+            (file, file_md) = (variant, md);
+            content_location = Some(location);
+        }
+    }
+
+    let path = content_location.clone().unwrap_or(path);
+
+    let ranges = config.server.config.ranges(&target);
+    let range = request.header::<ByteRange>("Range").filter(|_| ranges && matches!(request.method, Method::Get | Method::Head));
+
+    let compression = config.server.config.compression.as_ref().filter(|c| c.compresses(Path::new(&path)));
+    let compressible = compression.is_some();
+    let save_data = request.header::<String>("Save-Data").is_some_and(|v| v.trim().eq_ignore_ascii_case("on"));
+    let small = compression.is_some_and(|c| file_md.len() < c.min_size.unwrap_or(0));
+
+    let high_water = config.server.config.buffer_high_water.unwrap_or(65536);
+    let chunked = request.header::<String>("TE").is_none_or(|te| negotiate::accepts_chunked(&te));
+
+    let accepts_gzip = compressible
+        && file_md.len() > 0
+        && (chunked || file_md.len() <= high_water)
+        && (save_data || !small)
+        && request.header::<String>("Accept-Encoding").is_some_and(|ae| compress::accepts(&ae, "gzip"));
+
+    let selection = match range {
+        Some(range) => Selection::Range(range),
+        None if accepts_gzip => Selection::Gzip,
+        None => Selection::Identity,
     };
 
-    let mut file = File::open(&config.path(&path)).await?;
+    let gzip = matches!(selection, Selection::Gzip);
+    let mut etag = config.etag(&path, &file_md).await?;
+
+    if gzip {
+        etag.insert_str(etag.len() - 1, "-gzip");
+    }
+
+    let cache_control = config.server.config.cache_control(&target, Path::new(&path));
+
+    match conditional::evaluate(&request, Some(&etag)) {
+        Precondition::Proceed => (),
+        Precondition::NotModified => return Ok(Response::not_modified(Version::Http11, &etag, cache_control)),
+        Precondition::Failed => return error(412, config).await,
+    }
+
+    let compressed = config.server.compressed.as_ref().filter(|_| gzip);
+    let fspath = PathBuf::from(config.path(&path));
+    let revalidate = request.header::<String>("Cache-Control")
+        .is_some_and(|v| v.split(',').any(|d| d.trim().eq_ignore_ascii_case("no-cache") || d.trim().eq_ignore_ascii_case("no-store")));
+
+    let precompressed = compressed.and_then(|c| c.get(&fspath, &file_md)).filter(|_| !revalidate);
+
+    let mut response = match (selection, &precompressed) {
+        (Selection::Range(range), _) => match range.resolve(file_md.len()) {
+            Some((start, end)) => Response::serve_file_range(Version::Http11, file, start, end, file_md.len()).await?,
+            None => {
+                let mut response = error(416, config).await?;
+                response.headers.insert("Content-Range".into(), format!("bytes */{}", file_md.len()));
+                response.headers.insert("ETag".into(), etag);
+
+                return Ok(response);
+            },
+        },
+        (Selection::Gzip, None) if file_md.len() > high_water => {
+            let mut response = Response::serve_chunked(Version::Http11, 200, compress::GzipReader::new(file, 16384));
+            response.headers.insert("Content-Encoding".into(), "gzip".into());
+            response
+        },
+        (Selection::Gzip, Some((data, age))) => {
+            let mut response = Response::serve_bytes(Version::Http11, vec![]);
+            response.encoded("gzip", data.to_vec());
+            response.headers.insert("Age".into(), age.as_secs().to_string());
+            response
+        },
+        _ => if let Some((data, age)) = config.cached(&path, &mut file, &file_md, revalidate).await? {
+            let mut response = Response::serve_bytes(Version::Http11, data.to_vec());
+
+            if let Some(age) = age {
+                response.headers.insert("Age".into(), age.as_secs().to_string());
+            }
+
+            response
+        } else if !gzip && file_md.len() > high_water {
+            Response::serve_file_streaming(Version::Http11, file).await?
+        } else {
+            Response::serve_file(Version::Http11, &mut file, &fspath).await?
+        },
+    };
+
+    response.headers.insert("ETag".into(), etag);
+
+    response.headers.insert("Content-Type".into(), config.server.config.content_type(&path, &fspath).into());
+
+    if let Some(cache_control) = cache_control {
+        response.headers.insert("Cache-Control".into(), cache_control.into());
+    }
+
+    let vary: Vec<&str> = [(compressible, "Accept-Encoding"), (small, "Save-Data"), (negotiate, "Accept-Language")]
+        .into_iter()
+        .filter_map(|(enabled, header)| enabled.then_some(header))
+        .collect();
+
+    if !vary.is_empty() {
+        response.headers.insert("Vary".into(), vary.join(", "));
+    }
+
+    if !ranges {
+        response.disable_ranges();
+    }
+
+    if let Some(location) = content_location {
+        response.headers.insert("Content-Location".into(), location);
+    }
+
+    if gzip && precompressed.is_none() && !response.chunked() {
+        response.gzip()?;
+
+        if let Some(cache) = compressed {
+            cache.insert(&fspath, &file_md, Arc::new(response.body.clone()));
+        }
+    }
+
+    let wants_digest = config.server.config.digest.unwrap_or(false)
+        && request.header::<String>("Want-Digest").is_some_and(|w| negotiate::wants_digest(&w, "sha-256"));
+
+    let digest = match (wants_digest, gzip) {
+        (false, _) => None,
+        (true, true) => (!response.chunked()).then(|| etag::sha256(&response.body)),
+        (true, false) => Some(config.server.hashes.digest(&fspath, &file_md).await?),
+    };
+
+    if let Some(digest) = digest {
+        response.headers.insert("Digest".into(), format!("sha-256={}", etag::base64(&digest)));
+    }
 
-    Response::serve_file(Version::Http11, &mut file).await
+    Ok(response)
 }
@@ -0,0 +1,51 @@
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, path) = (pattern.as_bytes(), path.as_bytes());
+    let (mut p, mut s) = (0, 0);
+    let mut backtrack = None;
+
+    while s < path.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, s));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == path[s] {
+            p += 1;
+            s += 1;
+        } else if let Some((bp, bs)) = backtrack {
+            p = bp + 1;
+            s = bs + 1;
+            backtrack = Some((bp, bs + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literals() {
+        assert!(matches("/index.html", "/index.html"));
+        assert!(!matches("/index.html", "/index.htm"));
+        assert!(!matches("/index.htm", "/index.html"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*.css", "/static/site.css"));
+        assert!(matches("/static/*", "/static/"));
+        assert!(matches("/a/*/c/*.js", "/a/b/c/d.js"));
+        assert!(matches("*", ""));
+        assert!(!matches("*.css", "/static/site.js"));
+    }
+
+    #[test]
+    fn backtracks_over_repeated_prefixes() {
+        assert!(matches("*ab", "aaab"));
+        assert!(matches("*a*b", "xaxxab"));
+        assert!(!matches("*ab*c", "abab"));
+    }
+}
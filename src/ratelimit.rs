@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct BucketState {
+    tokens: f64,
+    last: Instant,
+}
+
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState { tokens: capacity, last: Instant::now() }),
+        }
+    }
+
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        state.tokens = (state.tokens + now.duration_since(state.last).as_secs_f64() * self.rate).min(self.capacity);
+        state.last = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / self.rate))
+        }
+    }
+
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+pub struct ConnectionLimiter {
+    max: usize,
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+pub struct ConnectionGuard {
+    ip: IpAddr,
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max: usize) -> Self {
+        Self { max, counts: Arc::default() }
+    }
+
+    pub fn try_acquire(&self, ip: IpAddr) -> Option<ConnectionGuard> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(ip).or_insert(0);
+
+        if *count >= self.max {
+            return None;
+        }
+
+        *count += 1;
+
+        Some(ConnectionGuard { ip, counts: self.counts.clone() })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::http::message::Message;
+use crate::http::response::Response;
+use crate::http::Deserialize as _;
+
+const REDACTED: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie"];
+const VOLATILE: &[&str] = &["date", "connection", "keep-alive"];
+const FRAMING: &[&str] = &["connection", "content-length", "transfer-encoding"];
+
+pub const MAX_BODY: usize = 1 << 20;
+
+#[derive(Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: String,
+    #[serde(default)]
+    pub request_body: Vec<u8>,
+    pub response: String,
+    pub body: Option<Vec<u8>>,
+}
+
+impl Exchange {
+    pub fn new(request: &str, request_body: Option<&[u8]>, response: &Response) -> Result<Self> {
+        Ok(Self {
+            request: redact(request),
+            request_body: request_body.unwrap_or_default().to_vec(),
+            response: redact(&response.head()?),
+            body: response.stream.is_none().then(|| response.body.clone()),
+        })
+    }
+}
+
+fn redact(head: &str) -> String {
+    head.split("\r\n")
+        .map(|line| match line.split_once(": ") {
+            Some((name, _)) if REDACTED.contains(&name.to_ascii_lowercase().as_str()) => format!("{name}: [redacted]"),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+pub struct Recorder {
+    lines: Sender<String>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, rx) = mpsc::channel::<String>();
+
+        thread::spawn(move || {
+            for line in rx {
+                if let Err(e) = writeln!(file, "{line}") {
+                    eprintln!("failed to write recording: {e}");
+                }
+            }
+        });
+
+        Ok(Self { lines })
+    }
+
+    pub fn record(&self, request: &str, body: Option<&[u8]>, response: &Response) -> Result<()> {
+        let exchange = Exchange::new(request, body, response)?;
+        let _ = self.lines.send(serde_json::to_string(&exchange)?);
+
+        Ok(())
+    }
+}
+
+fn comparable(head: &str) -> Vec<String> {
+    let mut lines: Vec<String> = head.split("\r\n")
+        .filter(|line| !line.is_empty())
+        .filter(|line| !line.split_once(": ").is_some_and(|(name, _)| VOLATILE.contains(&name.to_ascii_lowercase().as_str())))
+        .map(str::to_string)
+        .collect();
+
+    lines.sort();
+    lines
+}
+
+pub async fn replay(address: &str, exchange: &Exchange) -> Result<Vec<String>> {
+    let mut request: String = exchange.request.split("\r\n")
+        .take_while(|line| !line.is_empty())
+        .filter(|line| !line.split_once(": ").is_some_and(|(name, _)| FRAMING.contains(&name.to_ascii_lowercase().as_str())))
+        .map(|line| format!("{line}\r\n"))
+        .collect();
+
+    if !exchange.request_body.is_empty() {
+        request.push_str(&format!("Content-Length: {}\r\n", exchange.request_body.len()));
+    }
+
+    request.push_str("Connection: close\r\n\r\n");
+
+    let mut stream = TcpStream::connect(address).await?;
+    stream.write_all(request.as_bytes()).await?;
+    stream.write_all(&exchange.request_body).await?;
+
+    let Message::Response(response) = Message::deserialize(&mut BufReader::new(stream)).await? else {
+        return Ok(vec!["expected a response".into()]);
+    };
+
+    let expected = comparable(&exchange.response);
+    let actual = comparable(&response.head()?);
+
+    let mut diffs: Vec<String> = expected.iter().filter(|l| !actual.contains(l)).map(|l| format!("- {l}")).collect();
+    diffs.extend(actual.iter().filter(|l| !expected.contains(l)).map(|l| format!("+ {l}")));
+
+    if exchange.body.as_ref().is_some_and(|body| *body != response.body) {
+        diffs.push("body differs".into());
+    }
+
+    Ok(diffs)
+}
+
+pub async fn replay_file(path: &Path, address: &str) -> Result<usize> {
+    let mut failures = 0;
+
+    for line in fs::read_to_string(path)?.lines().filter(|l| !l.is_empty()) {
+        let exchange: Exchange = serde_json::from_str(line)?;
+        let diffs = replay(address, &exchange).await?;
+
+        if !diffs.is_empty() {
+            failures += 1;
+            println!("{}", exchange.request.lines().next().unwrap_or(""));
+
+            for diff in diffs {
+                println!("    {diff}");
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::net::TcpListener;
+
+    use crate::http::message::Version;
+    use crate::http::Serialize as _;
+
+    async fn serve_once(response: Vec<u8>) -> Result<(String, tokio::task::JoinHandle<Vec<u8>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let Message::Request(request) = Message::deserialize(&mut BufReader::new(&mut stream)).await.unwrap() else {
+                panic!("expected a request");
+            };
+
+            stream.write_all(&response).await.unwrap();
+            request.body
+        });
+
+        Ok((address, handle))
+    }
+
+    #[tokio::test]
+    async fn replays_a_recorded_round_trip() -> Result<()> {
+        let mut response = Response::from_bytes(Version::Http11, 201, b"created".to_vec());
+        response.headers.insert("Content-Type".into(), "text/plain".into());
+
+        let head = "PUT /uploads/a.txt HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Length: 5\r\n\r\n";
+        let exchange = Exchange::new(head, Some(b"hello"), &response)?;
+
+        assert!(exchange.request.contains("Authorization: [redacted]"));
+        assert!(!exchange.request.contains("secret"));
+
+        let exchange: Exchange = serde_json::from_str(&serde_json::to_string(&exchange)?)?;
+
+        let (address, server) = serve_once(response.serialize()?).await?;
+        let diffs = replay(&address, &exchange).await?;
+
+        assert!(diffs.is_empty(), "unexpected diffs: {diffs:?}");
+        assert_eq!(server.await?, b"hello");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_a_changed_response() -> Result<()> {
+        let recorded = Response::from_bytes(Version::Http11, 200, b"old".to_vec());
+        let exchange = Exchange::new("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n", None, &recorded)?;
+
+        let (address, server) = serve_once(Response::from_bytes(Version::Http11, 404, b"gone".to_vec()).serialize()?).await?;
+        let diffs = replay(&address, &exchange).await?;
+
+        assert!(diffs.contains(&"- HTTP/1.1 200 OK".to_string()));
+        assert!(diffs.contains(&"+ HTTP/1.1 404 Not Found".to_string()));
+        assert!(diffs.contains(&"body differs".to_string()));
+        assert!(server.await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::crypto::ring::sign::any_supported_type;
+use serde::Serialize;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, ServerConnection};
+use tokio_rustls::rustls::sign::CertifiedKey;
+
+pub fn load_certs(path: &Path) -> io::Result<Vec<pki_types::CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut io::BufReader::new(fs::File::open(path)?)).collect()
+}
+
+pub fn load_key(path: &Path) -> io::Result<Option<pki_types::PrivateKeyDer<'static>>> {
+    rustls_pemfile::private_key(&mut io::BufReader::new(fs::File::open(path)?))
+}
+
+pub fn load_certified_key(cert: &Path, key: &Path) -> io::Result<CertifiedKey> {
+    let certs = load_certs(cert)?;
+
+    if certs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no certificates found in file"));
+    }
+
+    let key = load_key(key)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no key found in file"))?;
+
+    let key = any_supported_type(&key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(CertifiedKey::new(certs, key))
+}
+
+#[derive(Debug)]
+pub struct CertResolver {
+    default: Arc<CertifiedKey>,
+    names: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl CertResolver {
+    pub fn new(default: CertifiedKey) -> Self {
+        Self { default: Arc::new(default), names: HashMap::new() }
+    }
+
+    pub fn scan(&mut self, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let cert = entry?.path();
+
+            if cert.extension().is_none_or(|ext| ext != "crt") {
+                continue;
+            }
+
+            let Some(name) = cert.file_stem().and_then(|s| s.to_str()) else {
+                eprintln!("warning: skipping certificate with non UTF-8 name {}", cert.display());
+                continue;
+            };
+
+            match load_certified_key(&cert, &cert.with_extension("key")) {
+                Ok(key) => {
+                    self.names.insert(name.to_ascii_lowercase(), Arc::new(key));
+                },
+                Err(e) => eprintln!("warning: skipping certificate {}: {e}", cert.display()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let key = client_hello.server_name()
+            .and_then(|name| self.names.get(&name.to_ascii_lowercase()))
+            .unwrap_or(&self.default);
+
+        Some(key.clone())
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct TlsInfo {
+    pub protocol: Option<String>,
+    pub cipher: Option<String>,
+    pub server_name: Option<String>,
+}
+
+impl TlsInfo {
+    pub fn new(connection: &ServerConnection) -> Self {
+        Self {
+            protocol: connection.protocol_version().map(|v| format!("{v:?}")),
+            cipher: connection.negotiated_cipher_suite().map(|s| format!("{:?}", s.suite())),
+            server_name: connection.server_name().map(Into::into),
+        }
+    }
+}
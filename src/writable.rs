@@ -0,0 +1,151 @@
+use std::io::SeekFrom;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use tokio::fs::{self, DirBuilder, OpenOptions};
+use tokio::io::{self, AsyncBufRead, AsyncSeekExt, AsyncWriteExt};
+
+use anyhow::Result;
+
+use crate::http::body::RequestBody;
+use crate::http::request::Request;
+
+#[derive(Clone, Copy)]
+pub struct Modes {
+    pub file: u32,
+    pub dir: u32,
+}
+
+impl Default for Modes {
+    fn default() -> Self {
+        Self { file: 0o600, dir: 0o700 }
+    }
+}
+
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    pub total: u64,
+}
+
+impl std::str::FromStr for ContentRange {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, total) = s.strip_prefix("bytes ").ok_or(())?.split_once('/').ok_or(())?;
+        let (start, end) = range.split_once('-').ok_or(())?;
+
+        let range = Self {
+            start: start.parse().map_err(|_| ())?,
+            end: end.parse().map_err(|_| ())?,
+            total: total.parse().map_err(|_| ())?,
+        };
+
+        if range.start > range.end || range.end >= range.total {
+            return Err(());
+        }
+
+        Ok(range)
+    }
+}
+
+#[cfg(unix)]
+async fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await
+}
+
+#[cfg(not(unix))]
+async fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+async fn create_dirs(dir: &Path, mode: u32) -> io::Result<()> {
+    let mut missing = vec![];
+
+    for ancestor in dir.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+        if fs::try_exists(ancestor).await? {
+            break;
+        }
+
+        missing.push(ancestor);
+    }
+
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    builder.mode(mode);
+
+    builder.create(dir).await?;
+
+    for created in missing.into_iter().rev() {
+        set_mode(created, mode).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn put<R: AsyncBufRead + Unpin>(path: &Path, request: &Request, body: &mut RequestBody<'_, R>, modes: Modes) -> Result<u32> {
+    let length = body.remaining();
+
+    let range = match request.header::<String>("Content-Range").map(|v| v.parse::<ContentRange>()) {
+        Some(Ok(range)) => Some(range),
+        Some(Err(())) => return Ok(400),
+        None => None,
+    };
+
+    let existing = fs::metadata(path).await.ok();
+
+    if let Some(range) = &range {
+        if length.is_some_and(|length| range.end - range.start + 1 != length) {
+            return Ok(400);
+        }
+
+        if existing.as_ref().is_some_and(|md| md.len() > range.total) {
+            return Ok(400);
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        create_dirs(parent, modes.dir).await?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(range.is_none());
+
+    #[cfg(unix)]
+    options.mode(modes.file);
+
+    let mut file = options.open(path).await?;
+
+    if existing.is_none() {
+        set_mode(path, modes.file).await?;
+    }
+
+    if let Some(range) = &range {
+        file.seek(SeekFrom::Start(range.start)).await?;
+    }
+
+    let copied = body.copy_to(&mut file).await?;
+
+    if length.is_some_and(|length| copied != length) {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    if range.as_ref().is_some_and(|range| range.end - range.start + 1 != copied) {
+        return Ok(400);
+    }
+
+    file.flush().await?;
+
+    Ok(if existing.is_some() { 204 } else { 201 })
+}
+
+pub async fn delete(path: &Path) -> Result<u32> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(204),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(404),
+        Err(e) => Err(e.into()),
+    }
+}